@@ -0,0 +1,162 @@
+use super::parse::Document;
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// directory next to the `.tex` file that bundled pictures are copied into
+const ASSETS_DIRECTORY: &str = "assets";
+
+/// hex characters of the content hash that are kept in the name of an asset
+const HASH_LENGTH: usize = 12;
+
+/// a picture copied into the assets directory
+#[derive(Debug, PartialEq)]
+pub(crate) struct Asset {
+    /// path relative to the `.tex` file, as it is included by latex
+    pub(crate) name: String,
+    pub(crate) source: PathBuf,
+}
+
+/// copy every picture into the assets directory and point the pictures of the document at the
+/// copies. `files` are the pictures of the document in order, as returned by
+/// `pictures::resolve`. Pictures with the same contents share a single asset
+pub(crate) fn copy_assets(
+    document: &mut Document,
+    files: &[PathBuf],
+    output_directory: &Path,
+) -> std::io::Result<Vec<Asset>> {
+    let assets_directory = output_directory.join(ASSETS_DIRECTORY);
+    std::fs::create_dir_all(&assets_directory)?;
+
+    let mut by_hash: HashMap<String, String> = HashMap::new();
+    let mut assets = Vec::new();
+
+    for (picture, file) in document.pictures_mut().into_iter().zip(files) {
+        let contents = std::fs::read(file)?;
+        let hash = content_hash(&contents);
+
+        let name = match by_hash.get(&hash) {
+            Some(name) => name.clone(),
+            None => {
+                let name = asset_name(file, &hash);
+                let destination = output_directory.join(&name);
+
+                // the hash is part of the name, so an existing asset already has these contents
+                if !destination.is_file() {
+                    std::fs::write(&destination, &contents)?;
+                }
+
+                assets.push(Asset {
+                    name: name.clone(),
+                    source: file.clone(),
+                });
+                by_hash.insert(hash, name.clone());
+                name
+            }
+        };
+
+        picture.path = name;
+    }
+
+    Ok(assets)
+}
+
+/// write the `.tex` file and its assets into a zip archive that can be uploaded as is
+pub(crate) fn write_zip(
+    path: &Path,
+    tex_name: &str,
+    tex: &[u8],
+    assets: &[Asset],
+) -> Result<(), zip::result::ZipError> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file(tex_name, options)?;
+    zip.write_all(tex)?;
+
+    for asset in assets {
+        zip.start_file(asset.name.as_str(), options)?;
+        zip.write_all(&std::fs::read(&asset.source)?)?;
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+fn content_hash(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `assets/<name>-<hash>.<extension>`, the original name keeps the assets recognizable
+fn asset_name(file: &Path, hash: &str) -> String {
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut name = format!("{}/{}-{}", ASSETS_DIRECTORY, stem, &hash[..HASH_LENGTH]);
+
+    if let Some(extension) = file.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_markdown;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn identical_pictures_share_an_asset() {
+        let directory = TempDir::new("bundle");
+        let output = directory.join("out");
+        std::fs::create_dir_all(&output).unwrap();
+        std::fs::write(directory.join("a.png"), b"same").unwrap();
+        std::fs::write(directory.join("b.png"), b"same").unwrap();
+        std::fs::write(directory.join("c.png"), b"different").unwrap();
+
+        let markdown =
+            "# Title\nAUTHOR=Author\n\n## Plots\n\n![](a.png)\n\n![](b.png)\n\n![](c.png)\n";
+        let mut document = parse_markdown(markdown.as_bytes()).unwrap();
+        let files: Vec<PathBuf> = ["a.png", "b.png", "c.png"]
+            .iter()
+            .map(|name| directory.join(name))
+            .collect();
+
+        let assets = copy_assets(&mut document, &files, &output).unwrap();
+        assert_eq!(assets.len(), 2);
+
+        let paths: Vec<String> = document
+            .pictures_mut()
+            .into_iter()
+            .map(|picture| picture.path.clone())
+            .collect();
+
+        assert_eq!(paths[0], paths[1]);
+        assert_ne!(paths[0], paths[2]);
+        assert!(paths[0].starts_with("assets/a-") && paths[0].ends_with(".png"));
+        assert!(output.join(&paths[2]).is_file());
+
+        let zip_path = output.join("talk.zip");
+        write_zip(&zip_path, "talk.tex", b"\\documentclass{beamer}", &assets).unwrap();
+
+        let archive = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+
+        let mut expected = vec!["talk.tex", paths[0].as_str(), paths[2].as_str()];
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+}
@@ -0,0 +1,332 @@
+use super::latex::SourceMap;
+
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::str::FromStr;
+
+/// most presentations settle after two passes, the third is for the table of contents and
+/// navigation of beamer
+const MAX_PASSES: usize = 3;
+
+/// messages in the log that mean another pass is required to get references right
+const RERUN_MESSAGES: [&str; 3] = ["Rerun to get", "Label(s) may have changed", "Rerun LaTeX"];
+
+/// lines of the output of an engine that are shown when it fails without a log
+const OUTPUT_LINES: usize = 20;
+
+/// files left behind by the tex engines that are removed after a successful compile
+const AUXILIARY_EXTENSIONS: [&str; 12] = [
+    "aux",
+    "log",
+    "nav",
+    "out",
+    "snm",
+    "toc",
+    "vrb",
+    "fls",
+    "fdb_latexmk",
+    "synctex.gz",
+    "xdv",
+    "bbl",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Engine {
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+    Latexmk,
+}
+
+impl Engine {
+    fn program(&self) -> &'static str {
+        match self {
+            Engine::Pdflatex => "pdflatex",
+            Engine::Xelatex => "xelatex",
+            Engine::Lualatex => "lualatex",
+            Engine::Latexmk => "latexmk",
+        }
+    }
+
+    fn command(&self, tex_file: &str) -> Command {
+        let mut command = Command::new(self.program());
+
+        if let Engine::Latexmk = self {
+            // latexmk decides how many passes are needed on its own
+            command.arg("-pdf");
+        }
+
+        command
+            .arg("-interaction=nonstopmode")
+            .arg("-halt-on-error")
+            .arg(tex_file);
+
+        command
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pdflatex" => Ok(Engine::Pdflatex),
+            "xelatex" => Ok(Engine::Xelatex),
+            "lualatex" => Ok(Engine::Lualatex),
+            "latexmk" => Ok(Engine::Latexmk),
+            other => Err(format!(
+                "unknown engine `{other}`, expected one of pdflatex, xelatex, lualatex or latexmk"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CompileError {
+    #[error("could not run `{program}`: {source}")]
+    Spawn {
+        program: &'static str,
+        source: std::io::Error,
+    },
+    #[error("`{program}` failed ({status}) without writing a log{output}")]
+    NoLog {
+        program: &'static str,
+        status: std::process::ExitStatus,
+        output: String,
+    },
+    #[error(
+        "`{program}` failed to compile the presentation, see `{log}` for details{}",
+        errors.iter().map(|e| format!("\n{e}")).collect::<String>()
+    )]
+    Latex {
+        program: &'static str,
+        log: String,
+        errors: Vec<LatexError>,
+    },
+}
+
+/// an error message from the log of a tex engine
+#[derive(Debug, PartialEq)]
+pub(crate) struct LatexError {
+    message: String,
+    // 1 indexed line of the .tex file
+    line: Option<usize>,
+    slide: Option<String>,
+}
+
+impl fmt::Display for LatexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "! {}", self.message)?;
+
+        if let Some(line) = self.line {
+            write!(f, "\n  --> line {line} of the .tex file")?;
+        }
+
+        if let Some(slide) = &self.slide {
+            write!(f, " (in slide `{slide}`)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// compile `tex_path` into a pdf next to it, rerunning the engine until references settle
+pub(crate) fn compile(
+    tex_path: &Path,
+    engine: Engine,
+    source_map: &SourceMap,
+) -> Result<(), CompileError> {
+    let program = engine.program();
+
+    // the engines write their output to the working directory
+    let directory = tex_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let tex_file = tex_path.file_name().unwrap().to_string_lossy().to_string();
+    let log_path = tex_path.with_extension("log");
+
+    let passes = match engine {
+        Engine::Latexmk => 1,
+        _ => MAX_PASSES,
+    };
+
+    // a log left over from an earlier run would be read as the errors of this one
+    let _ = std::fs::remove_file(&log_path);
+
+    for _ in 0..passes {
+        let output = engine
+            .command(&tex_file)
+            .current_dir(directory)
+            .output()
+            .map_err(|source| CompileError::Spawn { program, source })?;
+
+        let log = std::fs::read(&log_path)
+            .ok()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+
+        match log {
+            Some(log) if !output.status.success() => {
+                return Err(CompileError::Latex {
+                    program,
+                    log: log_path.display().to_string(),
+                    errors: parse_log(&log, source_map),
+                })
+            }
+            // the engine did not get as far as the document, like with a missing format file
+            None if !output.status.success() => {
+                return Err(CompileError::NoLog {
+                    program,
+                    status: output.status,
+                    output: output_tail(&output),
+                })
+            }
+            Some(log) if RERUN_MESSAGES.iter().any(|message| log.contains(message)) => (),
+            _ => break,
+        }
+    }
+
+    remove_auxiliary_files(tex_path);
+
+    Ok(())
+}
+
+/// the last lines the engine printed to stderr and stdout, indented below the error
+fn output_tail(output: &Output) -> String {
+    let mut tail = String::new();
+
+    for stream in [&output.stderr, &output.stdout] {
+        let text = String::from_utf8_lossy(stream);
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        for line in &lines[lines.len().saturating_sub(OUTPUT_LINES)..] {
+            tail.push_str("\n  ");
+            tail.push_str(line);
+        }
+    }
+
+    tail
+}
+
+/// find every `! error message` in a log and the `l.<number>` line it points at
+fn parse_log(log: &str, source_map: &SourceMap) -> Vec<LatexError> {
+    let mut errors = Vec::new();
+    let mut lines = log.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let message = match line.strip_prefix("! ") {
+            Some(message) => message.to_string(),
+            None => continue,
+        };
+
+        let mut tex_line = None;
+
+        // the line number follows a few lines of context, but comes before the next error
+        while let Some(next) = lines.peek() {
+            if next.starts_with("! ") {
+                break;
+            }
+
+            let next = lines.next().unwrap();
+
+            if let Some(number) = next.strip_prefix("l.") {
+                tex_line = number
+                    .split(|c: char| !c.is_ascii_digit())
+                    .next()
+                    .and_then(|number| number.parse().ok());
+                break;
+            }
+        }
+
+        errors.push(LatexError {
+            message,
+            line: tex_line,
+            slide: tex_line
+                .and_then(|line| source_map.slide_at(line))
+                .map(str::to_string),
+        });
+    }
+
+    errors
+}
+
+fn remove_auxiliary_files(tex_path: &Path) {
+    for extension in AUXILIARY_EXTENSIONS {
+        // most of these files will not exist for any given engine
+        let _ = std::fs::remove_file(tex_path.with_extension(extension));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_errors() {
+        let log = r"
+(./example.tex
+! Undefined control sequence.
+l.87 \foo
+         {bar}
+Here is how much of TeX's memory you used:
+! Emergency stop.
+<*> example.tex
+";
+
+        let source_map = SourceMap {
+            slides: vec![(60, "First".into()), (80, "Second".into())],
+        };
+        let errors = parse_log(log, &source_map);
+
+        let expected = vec![
+            LatexError {
+                message: "Undefined control sequence.".into(),
+                line: Some(87),
+                slide: Some("Second".into()),
+            },
+            LatexError {
+                message: "Emergency stop.".into(),
+                line: None,
+                slide: None,
+            },
+        ];
+
+        assert_eq!(errors, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failure_without_a_log() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        let stdout: String = (1..=30).map(|line| format!("line {line}\n")).collect();
+        let output = Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: stdout.into_bytes(),
+            stderr: b"I can't find the format file `pdflatex.fmt'!\n".to_vec(),
+        };
+
+        let error = CompileError::NoLog {
+            program: "pdflatex",
+            status: output.status,
+            output: output_tail(&output),
+        };
+        let message = error.to_string();
+
+        assert!(message.starts_with("`pdflatex` failed (exit status: 1) without writing a log\n"));
+        assert!(message.contains("\n  I can't find the format file `pdflatex.fmt'!\n  line 11\n"));
+        assert!(!message.contains("line 10\n"));
+        assert!(message.ends_with("line 30"));
+    }
+
+    #[test]
+    fn engine_names() {
+        assert_eq!("xelatex".parse::<Engine>(), Ok(Engine::Xelatex));
+        assert!("pdftex".parse::<Engine>().is_err());
+    }
+}
@@ -1,27 +1,135 @@
-use super::parse::{Block, BulletItem, ParsePicture, PictureDirective, Span};
+use super::metadata::Metadata;
+use super::parse::{
+    Block, BulletItem, BulletList, ColumnAlignment, Directive, ListKind, ParsePicture,
+    PictureDirective, ReferenceKind, Span,
+};
 
 use std::fmt::Write as FmtWrite;
-use std::io::Write as IoWrite;
+
+/// space between the text and a picture next to it, as a fraction of the width of the slide
+pub(crate) const SPLIT_GAP: f64 = 0.05;
 
 #[derive(Debug)]
 pub(crate) struct Presentation {
     pub(crate) title: Title,
-    pub(crate) author: String,
-    //author: String,
+    pub(crate) subtitle: Option<Title>,
+    pub(crate) metadata: Metadata,
     pub(crate) slides: Vec<Slide>,
+    pub(crate) sections: Vec<Section>,
+}
+
+/// a `\section` that starts before `slides[slide]`
+#[derive(Debug, PartialEq)]
+pub(crate) struct Section {
+    pub(crate) title: Title,
+    pub(crate) slide: usize,
 }
 
 #[derive(Debug)]
 pub(crate) struct Slide {
     pub(crate) title: Title,
     pub(crate) contents: ContentOptions,
+    pub(crate) notes: Vec<Block>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum ContentOptions {
     OnlyText(Vec<Block>),
-    OnlyPicture(Picture),
-    TextAndPicture(Vec<Block>, Picture),
+    OnlyPicture(Pictures),
+    TextAndPicture(Vec<Block>, Pictures),
+}
+
+/// every picture of a slide, laid out in rows of `columns` pictures
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Pictures {
+    pictures: Vec<Picture>,
+    columns: usize,
+}
+
+impl Pictures {
+    /// without an explicit number of columns up to three pictures share a row and four
+    /// pictures are placed in a 2x2 grid
+    pub(crate) fn new(pictures: Vec<Picture>, columns: Option<usize>) -> Self {
+        let columns = columns.unwrap_or(match pictures.len() {
+            4 => 2,
+            count => count.clamp(1, 3),
+        });
+
+        Self { pictures, columns }
+    }
+
+    /// the first picture placed anywhere other than the default right side decides where all of
+    /// them go
+    fn orientation(&self) -> Orientation {
+        self.pictures
+            .iter()
+            .map(|picture| picture.orientation)
+            .find(|orientation| *orientation != Orientation::Horizonal)
+            .unwrap_or(Orientation::Horizonal)
+    }
+
+    fn split(&self) -> Split {
+        self.pictures
+            .iter()
+            .find_map(|picture| picture.split)
+            .unwrap_or_default()
+    }
+
+    fn to_latex(&self, buffer: &mut String, is_split: bool) {
+        // a single picture keeps the layout it has always had
+        if let [picture] = self.pictures.as_slice() {
+            picture.to_latex_picture(is_split).to_latex(buffer);
+            return;
+        }
+
+        let columns = self.columns.min(self.pictures.len());
+        let rows = self.pictures.len().div_ceil(columns);
+
+        // leave a small gap between the pictures of a row
+        let width = ((1.0 - 0.03 * (columns - 1) as f64) / columns as f64 * 100.0).floor() / 100.0;
+        let height = if is_split { 0.6 } else { 0.7 } / rows as f64;
+
+        buffer.push_str(
+            r#"
+    \begin{figure}
+        \centering"#,
+        );
+
+        for (idx, picture) in self.pictures.iter().enumerate() {
+            if idx % columns == 0 && idx != 0 {
+                buffer.push_str("\n\n        \\medskip");
+            } else if idx != 0 {
+                buffer.push_str("\\hspace{0.03\\linewidth}%");
+            }
+
+            let picture_width = match &picture.width {
+                Some(width) => format!("width={}", width),
+                None => "width=\\linewidth".to_string(),
+            };
+            let picture_height = match &picture.height {
+                Some(height) => format!("height={}", height),
+                None => format!("height={:.2}\\paperheight", height),
+            };
+
+            write!(
+                buffer,
+                "\n        \\begin{{subfigure}}{{{width:.2}\\linewidth}}\n            \\centering\n            \\includegraphics[{picture_width},{picture_height},keepaspectratio]{{{}}}",
+                picture.path
+            )
+            .unwrap();
+
+            // every picture has its own caption
+            if let Some(caption) = &picture.caption {
+                buffer.push_str("\n            \\caption{");
+                buffer.push_str(&escape_latex(caption));
+                buffer.push('}');
+            }
+
+            buffer.push_str("\n        \\end{subfigure}");
+        }
+
+        buffer.push_str("\n    \\end{figure}");
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -31,22 +139,46 @@ pub(crate) struct Picture {
     orientation: Orientation,
     width: Option<String>,
     height: Option<String>,
+    split: Option<Split>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// where the picture goes relative to the text of the slide
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum Orientation {
+    // below the text
     Vertical,
+    // above the text
+    Top,
+    // to the left of the text
+    Left,
+    // to the right of the text
     Horizonal,
 }
 
+/// fractions of the width of the slide for the text and a picture next to each other
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Split {
+    text: f64,
+    picture: f64,
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Self {
+            text: 0.4,
+            picture: 1.0 - SPLIT_GAP - 0.4,
+        }
+    }
+}
+
 impl Picture {
-    fn to_latex_picture(&self, is_split: bool) -> LatexPicture {
+    fn to_latex_picture(&self, is_split: bool) -> LatexPicture<'_> {
         LatexPicture {
-            picture: &self,
+            picture: self,
             is_split,
-            width: self.width.as_ref().map(|x| x.as_str()),
+            width: self.width.as_deref(),
             // TODO: might need to fix this for split middle
-            height: self.height.as_ref().map(|x| x.as_str()),
+            height: self.height.as_deref(),
         }
     }
 }
@@ -59,39 +191,37 @@ impl From<ParsePicture> for Picture {
             directive,
         } = x;
 
-        let (width, height, orientation) = if let Some(directives) = directive {
-            let width: Option<String> = directives.iter().find_map(|x| match x {
-                PictureDirective::Vertical => None,
-                PictureDirective::Width(x) => Some(x.clone()),
-                PictureDirective::Height(_) => None,
-            });
-
-            let height: Option<String> = directives.iter().find_map(|x| match x {
-                PictureDirective::Vertical => None,
-                PictureDirective::Width(_) => None,
-                PictureDirective::Height(x) => Some(x.clone()),
-            });
+        // the first directive of each kind wins
+        let mut width = None;
+        let mut height = None;
+        let mut orientation = None;
+        let mut split = None;
 
-            let orientation: Orientation = directives
-                .iter()
-                .find_map(|x| match x {
-                    PictureDirective::Vertical => Some(Orientation::Vertical),
-                    PictureDirective::Width(_) => None,
-                    PictureDirective::Height(_) => None,
-                })
-                .unwrap_or(Orientation::Horizonal);
-
-            (width, height, orientation)
-        } else {
-            (None, None, Orientation::Horizonal)
-        };
+        for directive in directive.into_iter().flatten() {
+            match directive {
+                PictureDirective::Vertical => {
+                    orientation = orientation.or(Some(Orientation::Vertical))
+                }
+                PictureDirective::Top => orientation = orientation.or(Some(Orientation::Top)),
+                PictureDirective::Left => orientation = orientation.or(Some(Orientation::Left)),
+                PictureDirective::Right => {
+                    orientation = orientation.or(Some(Orientation::Horizonal))
+                }
+                PictureDirective::Width(x) => width = width.or(Some(x)),
+                PictureDirective::Height(x) => height = height.or(Some(x)),
+                PictureDirective::Split { text, picture } => {
+                    split = split.or(Some(Split { text, picture }))
+                }
+            }
+        }
 
         Self {
             path,
             caption,
             width,
             height,
-            orientation,
+            orientation: orientation.unwrap_or(Orientation::Horizonal),
+            split,
         }
     }
 }
@@ -108,7 +238,7 @@ impl Code {
     }
 }
 
-impl<'a> Latex for Code {
+impl Latex for Code {
     fn to_latex(&self, buffer: &mut String) {
         buffer.push_str(r#"\begin{lstlisting}[language="#);
         buffer.push_str(&self.language);
@@ -120,6 +250,94 @@ impl<'a> Latex for Code {
     }
 }
 
+/// tables with more columns than this are scaled down to fit the width of the slide
+const MAX_UNSCALED_TABLE_COLUMNS: usize = 5;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Table {
+    header: Vec<Vec<Span>>,
+    alignment: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span>>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Table {
+    pub(crate) fn new(
+        header: Vec<Vec<Span>>,
+        alignment: Vec<Alignment>,
+        rows: Vec<Vec<Vec<Span>>>,
+    ) -> Self {
+        Table {
+            header,
+            alignment,
+            rows,
+        }
+    }
+}
+
+impl Table {
+    pub(crate) fn cells_mut(&mut self) -> impl Iterator<Item = &mut Vec<Span>> {
+        self.header
+            .iter_mut()
+            .chain(self.rows.iter_mut().flat_map(|row| row.iter_mut()))
+    }
+}
+
+impl Latex for Table {
+    fn to_latex(&self, buffer: &mut String) {
+        let scaled = self.alignment.len() > MAX_UNSCALED_TABLE_COLUMNS;
+
+        buffer.push_str("\n\\begin{center}\n");
+
+        if scaled {
+            buffer.push_str("\\resizebox{\\textwidth}{!}{%\n");
+        }
+
+        buffer.push_str("\\begin{tabular}{");
+        for alignment in &self.alignment {
+            buffer.push(match alignment {
+                Alignment::Left => 'l',
+                Alignment::Center => 'c',
+                Alignment::Right => 'r',
+            });
+        }
+        buffer.push_str("}\n\\toprule\n");
+
+        write_table_row(buffer, &self.header);
+        buffer.push_str("\\midrule\n");
+
+        for row in &self.rows {
+            write_table_row(buffer, row);
+        }
+
+        buffer.push_str("\\bottomrule\n\\end{tabular}");
+
+        if scaled {
+            buffer.push_str("%\n}");
+        }
+
+        buffer.push_str("\n\\end{center}\n");
+    }
+}
+
+fn write_table_row(buffer: &mut String, row: &[Vec<Span>]) {
+    for (idx, cell) in row.iter().enumerate() {
+        if idx != 0 {
+            buffer.push_str(" & ");
+        }
+
+        cell.to_latex(buffer);
+    }
+
+    buffer.push_str(" \\\\\n");
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) struct LatexPicture<'a> {
     picture: &'a Picture,
@@ -134,7 +352,7 @@ impl<'a> LatexPicture<'a> {
     }
 
     fn caption(&self) -> Option<&str> {
-        self.picture.caption.as_ref().map(|x| x.as_str())
+        self.picture.caption.as_deref()
     }
 }
 
@@ -149,6 +367,33 @@ impl From<Vec<Span>> for Title {
     }
 }
 
+impl Title {
+    /// the title without any formatting, for messages to the user
+    pub(crate) fn plain_text(&self) -> String {
+        let mut text = String::new();
+        push_plain_text(&self.title, &mut text);
+        text
+    }
+}
+
+fn push_plain_text(spans: &[Span], text: &mut String) {
+    for span in spans {
+        match span {
+            Span::Bold(inner)
+            | Span::Italics(inner)
+            | Span::Strikethrough(inner)
+            | Span::Link { text: inner, .. }
+            | Span::ReferenceLink { text: inner, .. } => push_plain_text(inner, text),
+            Span::Text(s)
+            | Span::Equation(s)
+            | Span::Math(s)
+            | Span::Code(s)
+            | Span::Url(s)
+            | Span::Latex(s) => text.push_str(s),
+        }
+    }
+}
+
 pub(crate) trait Latex {
     fn to_latex(&self, buffer: &mut String);
 }
@@ -169,7 +414,11 @@ impl<'a> Latex for LatexPicture<'a> {
         );
 
         let make_include_graphics = |buffer: &mut String, height: &str, width: &str| {
-            write!(buffer, "\\includegraphics[{width},{height},keepaspectratio]{{").unwrap();
+            write!(
+                buffer,
+                "\\includegraphics[{width},{height},keepaspectratio]{{"
+            )
+            .unwrap();
             //buffer.push_str("\n\\includegraphics[");
             //buffer.push_str(width);
             //buffer.push_str(",");
@@ -216,13 +465,13 @@ impl<'a> Latex for LatexPicture<'a> {
         }
 
         buffer.push_str(&path);
-        buffer.push_str(r#"}"#);
+        buffer.push('}');
 
         // only add a caption if we have one
         if let Some(caption) = self.caption() {
             buffer.push_str(r#"\caption{"#);
-            buffer.push_str(caption);
-            buffer.push_str(r#"}"#);
+            buffer.push_str(&escape_latex(caption));
+            buffer.push('}');
         }
 
         buffer.push_str(r#"\end{figure}"#)
@@ -232,43 +481,97 @@ impl<'a> Latex for LatexPicture<'a> {
 impl Latex for ContentOptions {
     fn to_latex(&self, buffer: &mut String) {
         match self {
-            ContentOptions::OnlyText(content_list) => {
-                for c in content_list {
-                    c.to_latex(buffer);
-                    buffer.push_str("\n\n")
-                }
-            }
-            ContentOptions::OnlyPicture(picture) => {
-                picture.to_latex_picture(false).to_latex(buffer);
+            // pictures are only left in the text when the slide has explicit columns
+            ContentOptions::OnlyText(content_list) => nested_blocks(content_list, buffer),
+            ContentOptions::OnlyPicture(pictures) => {
+                pictures.to_latex(buffer, false);
             }
-            ContentOptions::TextAndPicture(content, picture) => match picture.orientation {
-                Orientation::Vertical => {
-                    for c in content {
-                        c.to_latex(buffer)
-                    }
+            ContentOptions::TextAndPicture(content, pictures) => {
+                let split = pictures.split();
 
-                    picture.to_latex_picture(false).to_latex(buffer);
-                }
-                Orientation::Horizonal => {
-                    buffer.push_str("\t\\begin{minipage}{0.4\\textwidth}\n");
+                let text = |buffer: &mut String| {
+                    writeln!(
+                        buffer,
+                        "\t\\begin{{minipage}}{{{}\\textwidth}}",
+                        round_width(split.text)
+                    )
+                    .unwrap();
 
                     for c in content {
                         c.to_latex(buffer)
                     }
 
                     buffer.push_str("\n\t\\end{minipage}%\n");
-                    buffer.push_str("\t\\hfill\n");
-                    buffer.push_str("\t\\begin{minipage}{0.55\\textwidth}\n");
+                };
 
-                    picture.to_latex_picture(true).to_latex(buffer);
+                let picture = |buffer: &mut String| {
+                    writeln!(
+                        buffer,
+                        "\t\\begin{{minipage}}{{{}\\textwidth}}",
+                        round_width(split.picture)
+                    )
+                    .unwrap();
+
+                    pictures.to_latex(buffer, true);
 
                     buffer.push_str("\t\\end{minipage}\n");
+                };
+
+                match pictures.orientation() {
+                    Orientation::Vertical => {
+                        for c in content {
+                            c.to_latex(buffer)
+                        }
+
+                        pictures.to_latex(buffer, false);
+                    }
+                    Orientation::Top => {
+                        pictures.to_latex(buffer, false);
+                        buffer.push('\n');
+
+                        for c in content {
+                            c.to_latex(buffer)
+                        }
+                    }
+                    Orientation::Horizonal => {
+                        text(buffer);
+                        buffer.push_str("\t\\hfill\n");
+                        picture(buffer);
+                    }
+                    Orientation::Left => {
+                        picture(buffer);
+                        buffer.push_str("\t\\hfill\n");
+                        text(buffer);
+                    }
                 }
-            },
+            }
         }
     }
 }
 
+/// `0.55` instead of the `0.5499999999999999` left over from subtracting the gap
+fn round_width(fraction: f64) -> f64 {
+    (fraction * 1000.0).round() / 1000.0
+}
+
+/// blocks inside of a block, quote or notes, where pictures are placed inline instead of being laid out
+/// with the text of the slide
+pub(crate) fn nested_blocks(blocks: &[Block], buffer: &mut String) {
+    for block in blocks {
+        match block {
+            Block::Picture(picture) => Picture::from(picture.clone())
+                .to_latex_picture(true)
+                .to_latex(buffer),
+            Block::Directive(Directive::Pause) => buffer.push_str("\\pause"),
+            // %NEWSLIDE only splits slides at the top level, and notes only belong to slides
+            Block::Directive(_) | Block::Notes(_) | Block::LinkDefinition { .. } => continue,
+            block => block.to_latex(buffer),
+        }
+
+        buffer.push_str("\n\n");
+    }
+}
+
 impl Latex for Block {
     fn to_latex(&self, buffer: &mut String) {
         match self {
@@ -277,36 +580,133 @@ impl Latex for Block {
             Block::Picture(_) => {
                 panic!("pictures should be removed from blocks prior to processing")
             }
+            Block::Directive(Directive::Pause) => buffer.push_str("\\pause"),
             Block::Directive(_) => {
                 panic!("directives should have been remove from the presentation as a postprocess step")
             }
+            // definitions are removed when resolving reference links, and have no text of their own
+            Block::LinkDefinition { .. } => (),
+            Block::Notes(_) => {
+                panic!("speaker notes should have been moved to their slide while parsing")
+            }
             Block::Code(code) => code.to_latex(buffer),
+            Block::Table(table) => table.to_latex(buffer),
+            Block::Latex(latex) => buffer.push_str(latex),
+            Block::Equation(equation) => display_equation(buffer, equation),
+            Block::Heading { level, text } => {
+                // `###` is a size larger than `####`
+                let size = if *level == 3 {
+                    "\\large"
+                } else {
+                    "\\normalsize"
+                };
+                write!(buffer, "{{{}\\textbf{{", size).unwrap();
+                text.to_latex(buffer);
+                buffer.push_str("}}");
+            }
+            Block::BeamerBlock(group) => {
+                let environment = group.kind.environment();
+
+                write!(buffer, "\\begin{{{}}}{{", environment).unwrap();
+                group.title.to_latex(buffer);
+                buffer.push_str("}\n");
+                nested_blocks(&group.contents, buffer);
+                write!(buffer, "\\end{{{}}}", environment).unwrap();
+            }
+            Block::Quote(blocks) => {
+                buffer.push_str("\\begin{quote}\n");
+                nested_blocks(blocks, buffer);
+                buffer.push_str("\\end{quote}");
+            }
+            Block::Columns(columns) => {
+                let alignment = match columns.alignment {
+                    ColumnAlignment::Top => "T",
+                    ColumnAlignment::Center => "c",
+                };
+
+                writeln!(buffer, "\\begin{{columns}}[{}]", alignment).unwrap();
+
+                for column in &columns.columns {
+                    writeln!(
+                        buffer,
+                        "\\begin{{column}}{{{:.3}\\textwidth}}",
+                        column.width
+                    )
+                    .unwrap();
+                    nested_blocks(&column.contents, buffer);
+                    buffer.push_str("\\end{column}\n");
+                }
+
+                buffer.push_str("\\end{columns}");
+            }
         }
     }
 }
 
-impl Latex for Vec<BulletItem> {
-    fn to_latex(&self, buffer: &mut String) {
-        buffer.push_str("\n\\begin{itemize}\n");
+fn display_equation(buffer: &mut String, equation: &str) {
+    // equations broken over multiple lines need an environment that allows `\\` and `&`
+    let (start, end) = if equation.contains("\\\\") {
+        ("\\begin{align*}", "\\end{align*}")
+    } else {
+        ("\\[", "\\]")
+    };
 
-        for item in self.iter() {
-            item.to_latex(buffer)
-        }
+    write!(buffer, "\n{start}\n{}\n{end}\n", equation.trim()).unwrap();
+}
 
-        buffer.push_str("\n\\end{itemize}\n");
+impl Latex for BulletList {
+    fn to_latex(&self, buffer: &mut String) {
+        self.write_list(buffer, 0)
     }
 }
 
-impl Latex for BulletItem {
-    fn to_latex(&self, buffer: &mut String) {
-        match self {
-            BulletItem::Single(spans) => {
-                buffer.push_str("\\item ");
-                spans.to_latex(buffer);
-                buffer.push('\n');
+/// counters used by latex for each nesting level of `enumerate`. latex stops with a "too deeply
+/// nested" error for a fifth level, so there is no counter to set for it
+const ENUMERATE_COUNTERS: [&str; 4] = ["enumi", "enumii", "enumiii", "enumiv"];
+
+impl BulletList {
+    /// `enumerate_depth` is the number of `enumerate` environments this list is nested
+    /// inside of, which decides what counter a non-1 start number is written to
+    fn write_list(&self, buffer: &mut String, enumerate_depth: usize) {
+        let environment = match self.kind {
+            ListKind::Unordered => "itemize",
+            ListKind::Ordered { .. } => "enumerate",
+        };
+
+        if self.incremental {
+            // each item is shown on its own overlay
+            write!(buffer, "\n\\begin{{{environment}}}[<+->]\n").unwrap();
+        } else {
+            write!(buffer, "\n\\begin{{{environment}}}\n").unwrap();
+        }
+
+        if let ListKind::Ordered { start } = self.kind {
+            if start != 1 {
+                if let Some(counter) = ENUMERATE_COUNTERS.get(enumerate_depth) {
+                    // the counter is incremented before the first item is printed, so a list
+                    // starting at 0 sets it to -1
+                    writeln!(buffer, "\\setcounter{{{counter}}}{{{}}}", start as i64 - 1).unwrap();
+                }
+            }
+        }
+
+        let nested_depth = match self.kind {
+            ListKind::Unordered => enumerate_depth,
+            ListKind::Ordered { .. } => enumerate_depth + 1,
+        };
+
+        for item in self.items.iter() {
+            match item {
+                BulletItem::Single(spans) => {
+                    buffer.push_str("\\item ");
+                    spans.to_latex(buffer);
+                    buffer.push('\n');
+                }
+                BulletItem::Nested(list) => list.write_list(buffer, nested_depth),
             }
-            BulletItem::Nested(item_list) => item_list.to_latex(buffer),
         }
+
+        write!(buffer, "\n\\end{{{environment}}}\n").unwrap();
     }
 }
 
@@ -321,13 +721,71 @@ impl Latex for Vec<Span> {
 impl Latex for Span {
     fn to_latex(&self, buffer: &mut String) {
         match self {
-            Span::Bold(s) => wrap_text(buffer, "\\textbf{", &s, "}"),
-            Span::Strikethrough(s) => wrap_text(buffer, "\\sout{", &s, "}"),
-            Span::Italics(s) => wrap_text(buffer, "\\emph{", &s, "}"),
-            Span::Text(s) => wrap_text(buffer, "", &s, ""),
-            Span::Equation(s) => wrap_text(buffer, "$$", &s, "$$"),
+            Span::Bold(spans) => wrap_spans(buffer, "\\textbf{", spans, "}"),
+            Span::Strikethrough(spans) => wrap_spans(buffer, "\\sout{", spans, "}"),
+            Span::Italics(spans) => wrap_spans(buffer, "\\emph{", spans, "}"),
+            Span::Text(s) => wrap_text(buffer, "", &escape_latex(s), ""),
+            // equations and raw latex are written exactly as the user wrote them
+            Span::Equation(s) => wrap_text(buffer, "\\[", s, "\\]"),
+            Span::Math(s) => wrap_text(buffer, "$", s, "$"),
+            Span::Latex(s) => wrap_text(buffer, "", s, ""),
+            Span::Code(s) => wrap_text(buffer, "\\texttt{", &escape_latex(s), "}"),
+            // `[text]()` has nowhere to go, so it is only the text
+            Span::Link { text, url } if url.trim().is_empty() => text.to_latex(buffer),
+            Span::Link { text, url } => {
+                write!(buffer, "\\href{{{}}}", escape_url(url)).unwrap();
+                wrap_spans(buffer, "{", text, "}");
+            }
+            Span::Url(url) => wrap_text(buffer, "\\url{", &escape_url(url), "}"),
+            // the label was never defined, so this was not meant to be a link
+            Span::ReferenceLink { text, label, kind } => {
+                wrap_spans(buffer, "[", text, "]");
+
+                match kind {
+                    ReferenceKind::Full => wrap_text(buffer, "[", &escape_latex(label), "]"),
+                    ReferenceKind::Collapsed => buffer.push_str("[]"),
+                    ReferenceKind::Shortcut => (),
+                }
+            }
+        }
+    }
+}
+
+/// escape every character that latex would otherwise interpret as a command so that `text`
+/// is typeset exactly as written
+pub(crate) fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '<' => escaped.push_str("\\textless{}"),
+            '>' => escaped.push_str("\\textgreater{}"),
+            '|' => escaped.push_str("\\textbar{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
         }
     }
+
+    escaped
+}
+
+/// `%` and `#` still have to be escaped inside of `\href` and `\url`
+fn escape_url(url: &str) -> String {
+    url.replace('%', "\\%").replace('#', "\\#")
+}
+
+fn wrap_spans(buffer: &mut String, start: &'static str, inner: &[Span], end: &'static str) {
+    buffer.push_str(start);
+    for span in inner {
+        span.to_latex(buffer);
+    }
+    buffer.push_str(end);
 }
 
 fn wrap_text(buffer: &mut String, start: &'static str, inner: &str, end: &'static str) {
@@ -335,3 +793,159 @@ fn wrap_text(buffer: &mut String, start: &'static str, inner: &str, end: &'stati
     buffer.push_str(inner);
     buffer.push_str(end);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_special_characters() {
+        let out = escape_latex(r"100% of #1 & {x_y} ~ a^b \ $5");
+        assert_eq!(
+            out,
+            r"100\% of \#1 \& \{x\_y\} \textasciitilde{} a\textasciicircum{}b \textbackslash{} \$5"
+        );
+    }
+
+    #[test]
+    fn ordered_list_start() {
+        let list = |start, items| BulletList {
+            kind: ListKind::Ordered { start },
+            items,
+            incremental: false,
+        };
+        let item = || BulletItem::Single(vec![Span::Text("item".into())]);
+
+        let mut buffer = String::new();
+        list(0, vec![item(), BulletItem::Nested(list(5, vec![item()]))]).to_latex(&mut buffer);
+
+        assert!(buffer.contains("\\begin{enumerate}\n\\setcounter{enumi}{-1}\n"));
+        assert!(buffer.contains("\\begin{enumerate}\n\\setcounter{enumii}{4}\n"));
+
+        let mut buffer = String::new();
+        list(1, vec![item()]).to_latex(&mut buffer);
+
+        assert!(!buffer.contains("setcounter"));
+    }
+
+    #[test]
+    fn nested_spans() {
+        let spans = vec![Span::Bold(vec![
+            Span::Text("a & ".into()),
+            Span::Italics(vec![Span::Text("b".into())]),
+        ])];
+
+        let mut buffer = String::new();
+        spans.to_latex(&mut buffer);
+
+        assert_eq!(buffer, r"\textbf{a \& \emph{b}}");
+    }
+
+    #[test]
+    fn links() {
+        let spans = vec![
+            Span::Link {
+                text: vec![Span::Text("50%".into())],
+                url: "https://example.com/100%#top".into(),
+            },
+            Span::Url("https://example.com/#a".into()),
+        ];
+
+        let mut buffer = String::new();
+        spans.to_latex(&mut buffer);
+
+        assert_eq!(
+            buffer,
+            r"\href{https://example.com/100\%\#top}{50\%}\url{https://example.com/\#a}"
+        );
+    }
+
+    #[test]
+    fn equations_and_raw_latex_are_not_escaped() {
+        let spans = vec![
+            Span::Text("50% ".into()),
+            Span::Math("x_1^2".into()),
+            Span::Latex(r"\hfill".into()),
+        ];
+
+        let mut buffer = String::new();
+        spans.to_latex(&mut buffer);
+
+        assert_eq!(buffer, r"50\% $x_1^2$\hfill");
+    }
+
+    #[test]
+    fn pictures_in_a_grid() {
+        let picture = |path: &str, caption: Option<&str>| {
+            Picture::from(ParsePicture {
+                path: path.into(),
+                caption: caption.map(String::from),
+                directive: None,
+            })
+        };
+
+        let pictures = Pictures::new(
+            vec![
+                picture("a.png", Some("50% faster")),
+                picture("b.png", None),
+                picture("c.png", None),
+                picture("d.png", None),
+            ],
+            None,
+        );
+        assert_eq!(pictures.columns, 2);
+
+        let mut buffer = String::new();
+        pictures.to_latex(&mut buffer, false);
+
+        assert_eq!(
+            buffer
+                .matches("\\begin{subfigure}{0.48\\linewidth}")
+                .count(),
+            4
+        );
+        assert_eq!(buffer.matches("\\medskip").count(), 1);
+        assert_eq!(buffer.matches("\\hspace").count(), 2);
+        assert!(buffer.contains("height=0.35\\paperheight"));
+        assert!(buffer.contains("\\caption{50\\% faster}"));
+        assert_eq!(buffer.matches("\\caption").count(), 1);
+
+        // one picture is rendered the way it always has been
+        let single = Pictures::new(vec![picture("a.png", None)], None);
+        let mut buffer = String::new();
+        single.to_latex(&mut buffer, false);
+
+        let mut expected = String::new();
+        picture("a.png", None)
+            .to_latex_picture(false)
+            .to_latex(&mut expected);
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn picture_left_of_the_text() {
+        let picture = Picture::from(ParsePicture {
+            path: "a.png".into(),
+            caption: None,
+            directive: Some(vec![
+                PictureDirective::Left,
+                PictureDirective::Split {
+                    text: 0.3,
+                    picture: 1.0 - SPLIT_GAP - 0.3,
+                },
+            ]),
+        });
+
+        let contents = ContentOptions::TextAndPicture(
+            vec![Block::Paragraph(vec![Span::Text("text".into())])],
+            Pictures::new(vec![picture], None),
+        );
+
+        let mut buffer = String::new();
+        contents.to_latex(&mut buffer);
+
+        let picture_start = buffer.find("\\begin{minipage}{0.65\\textwidth}").unwrap();
+        let text_start = buffer.find("\\begin{minipage}{0.3\\textwidth}").unwrap();
+        assert!(picture_start < text_start);
+    }
+}
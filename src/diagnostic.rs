@@ -0,0 +1,192 @@
+use std::fmt;
+
+/// error type used by every parser in `parse.rs`. `input` is the slice of the markdown
+/// where the error occured, which is later turned back into a line and column
+#[derive(Debug, PartialEq)]
+pub(crate) struct ParseError<I> {
+    pub(crate) input: I,
+    pub(crate) kind: ParseErrorKind,
+}
+
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub(crate) enum ParseErrorKind {
+    #[error("unexpected input while parsing ({})", .0.description())]
+    Nom(nom::error::ErrorKind),
+    #[error(
+        "expected the presentation to start with a `# Title` line or a `title` in the front matter"
+    )]
+    MissingTitle,
+    #[error("expected an `AUTHOR=` line directly after the title")]
+    MissingAuthor,
+    #[error("text outside of a slide, start a slide with a `## Title` line before it")]
+    TextOutsideSlide,
+    #[error("unterminated code block, expected a closing ```")]
+    UnterminatedCodeBlock,
+    #[error("unterminated equation, expected a closing `{0}`")]
+    UnterminatedEquation(&'static str),
+    #[error("unterminated speaker notes, expected a closing `-->`")]
+    UnterminatedNotes,
+    #[error("code blocks can not be used in speaker notes, use `inline code` instead")]
+    CodeInNotes,
+    #[error("unterminated front matter, expected a closing `{0}` line")]
+    UnterminatedFrontMatter(&'static str),
+    #[error("invalid front matter: {0}")]
+    FrontMatter(String),
+    #[error("expected a positive number of columns after `%PICTURECOLUMNS=`")]
+    InvalidPictureColumns,
+    #[error("unknown option `{0}` for `%COLUMNS`, expected TOP, CENTER or a positive width ratio")]
+    InvalidColumnOption(String),
+    #[error("width ratios `{0}` add up to more than 1, use ratios like `2,1` for relative widths")]
+    InvalidColumnRatios(String),
+    #[error("`%COLUMNS` is not closed by an `%ENDCOLUMNS` on the same slide")]
+    UnclosedColumns,
+    #[error("`{0}` outside of `%COLUMNS` and `%ENDCOLUMNS`")]
+    StrayColumnDirective(&'static str),
+    #[error(
+        "invalid `%SPLIT={0}`, expected the width of the text and optionally of the picture, adding up to at most 1"
+    )]
+    InvalidSplit(String),
+    #[error("picture `{0}` does not exist")]
+    MissingPicture(String),
+    #[error(
+        "pictures with the extension `{0}` can not be included by pdflatex, convert it to png, jpg or pdf"
+    )]
+    UnsupportedPicture(String),
+}
+
+impl<I> ParseError<I> {
+    pub(crate) fn new(input: I, kind: nom::error::ErrorKind) -> Self {
+        Self {
+            input,
+            kind: ParseErrorKind::Nom(kind),
+        }
+    }
+
+    /// an error that should stop all parsing, instead of letting the parser try something else
+    pub(crate) fn failure(input: I, kind: ParseErrorKind) -> nom::Err<Self> {
+        nom::Err::Failure(Self { input, kind })
+    }
+}
+
+impl<I> nom::error::ParseError<I> for ParseError<I> {
+    fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
+        Self::new(input, kind)
+    }
+
+    fn append(_input: I, _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// a parsing error located in the original markdown text
+#[derive(Debug)]
+pub(crate) struct Diagnostic {
+    pub(crate) kind: ParseErrorKind,
+    // 1 indexed
+    pub(crate) line: usize,
+    // 1 indexed, in characters
+    pub(crate) column: usize,
+    source_line: String,
+    slide: Option<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(text: &str, error: ParseError<&str>) -> Self {
+        let offset = offset_in(text, error.input);
+        let before = &text[..offset];
+
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = text[offset..]
+            .find('\n')
+            .map_or(text.len(), |idx| offset + idx);
+
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+
+        // the last slide header before the error is the slide that was being parsed
+        let slide = before
+            .lines()
+            .rev()
+            .map(str::trim_start)
+            .find(|line| line.starts_with("## "))
+            .map(|line| line[3..].trim().to_string());
+
+        Self {
+            kind: error.kind,
+            line,
+            column,
+            source_line: text[line_start..line_end].to_string(),
+            slide,
+        }
+    }
+}
+
+/// byte offset of `input` inside of `text`. Inputs that do not point into `text` are placed
+/// at the end of it
+fn offset_in(text: &str, input: &str) -> usize {
+    let start = text.as_ptr() as usize;
+    let position = input.as_ptr() as usize;
+
+    if position >= start && position <= start + text.len() {
+        position - start
+    } else {
+        text.len()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        writeln!(f, "{}", self.kind)?;
+        write!(f, "{gutter}--> line {}, column {}", self.line, self.column)?;
+
+        if let Some(slide) = &self.slide {
+            write!(f, " (in slide `{slide}`)")?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line_number} | {}", self.source_line)?;
+
+        // keep tabs so the caret lines up with the source line
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        write!(f, "{gutter} | {padding}^")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_and_column() {
+        let text = "# Title\nAUTHOR=me\n\n## Slide\n\nsome $$text";
+        let position = text.find("$$").unwrap();
+        let error = ParseError {
+            input: &text[position..],
+            kind: ParseErrorKind::UnterminatedEquation("$$"),
+        };
+
+        let diagnostic = Diagnostic::new(text, error);
+
+        assert_eq!(diagnostic.line, 6);
+        assert_eq!(diagnostic.column, 6);
+        assert_eq!(diagnostic.slide.as_deref(), Some("Slide"));
+
+        let expected = "unterminated equation, expected a closing `$$`
+ --> line 6, column 6 (in slide `Slide`)
+  |
+6 | some $$text
+  |      ^";
+
+        assert_eq!(diagnostic.to_string(), expected);
+    }
+}
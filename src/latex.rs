@@ -1,18 +1,161 @@
-use super::data::{Latex, Presentation, Slide, Title};
+use super::data::{escape_latex, nested_blocks, Latex, Presentation, Section, Slide, Title};
+use super::metadata::{Date, Metadata, NotesMode};
+use super::template::{Segment, Template, TemplateError};
 use super::Error;
-use std::io::Write;
+
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+/// the line of the generated `.tex` file that each slide starts on, so that errors
+/// reported by latex can be traced back to the markdown
+#[derive(Debug, Default)]
+pub(crate) struct SourceMap {
+    // (1 indexed line, slide title)
+    pub(crate) slides: Vec<(usize, String)>,
+}
+
+impl SourceMap {
+    /// title of the slide that contains `line` of the `.tex` file
+    pub(crate) fn slide_at(&self, line: usize) -> Option<&str> {
+        self.slides
+            .iter()
+            .take_while(|(start, _)| *start <= line)
+            .last()
+            .map(|(_, title)| title.as_str())
+    }
+}
 
 pub(crate) fn write_latex<W: Write>(
-    mut writer: W,
+    writer: W,
     presentation: Presentation,
-) -> Result<(), Error> {
-    latex_header(&mut writer, presentation.title, presentation.author)?;
+    template: &Template,
+    preamble_extra: &str,
+) -> Result<SourceMap, Error> {
+    if !template.has_placeholder("slides") {
+        return Err(TemplateError::MissingPlaceholder("slides").into());
+    }
+
+    if !preamble_extra.is_empty() && !template.has_placeholder("preamble_extra") {
+        return Err(TemplateError::MissingPlaceholder("preamble_extra").into());
+    }
+
+    if presentation.metadata.notes != NotesMode::Hide && !template.has_placeholder("notes") {
+        return Err(TemplateError::MissingPlaceholder("notes").into());
+    }
+
+    // slides are written first, their lines in the .tex depend on where the template puts them
+    let mut slides = LineCounter::new(Vec::new());
+    let mut slide_lines = Vec::new();
+
+    let agenda = presentation.metadata.agenda;
+    let mut sections = presentation.sections.into_iter().peekable();
+
+    for (idx, slide) in presentation.slides.into_iter().enumerate() {
+        while let Some(section) = sections.next_if(|section| section.slide == idx) {
+            write_section(&mut slides, section, agenda)?;
+        }
+
+        slide_lines.push((slides.lines, slide.title.plain_text()));
+        write_slide(&mut slides, slide)?;
+    }
+
+    for section in sections {
+        write_section(&mut slides, section, agenda)?;
+    }
+
+    let mut values = header_values(
+        presentation.title,
+        presentation.subtitle,
+        &presentation.metadata,
+    );
+
+    let mut preamble_extra = preamble_extra.to_string();
+    if !preamble_extra.is_empty() && !preamble_extra.ends_with('\n') {
+        preamble_extra.push('\n');
+    }
+    values.insert("preamble_extra".into(), preamble_extra);
 
-    for slide in presentation.slides {
-        write_slide(&mut writer, slide)?;
+    let mut writer = LineCounter::new(writer);
+    let mut source_map = SourceMap::default();
+
+    for (idx, segment) in template.segments().iter().enumerate() {
+        match segment {
+            Segment::Text(text) => writer.write_all(text.as_bytes())?,
+            Segment::Placeholder(name) if name == "slides" => {
+                let start = writer.lines + 1;
+                source_map.slides.extend(
+                    slide_lines
+                        .iter()
+                        .map(|(line, title)| (start + line, title.clone())),
+                );
+
+                writer.write_all(&slides.writer)?;
+            }
+            Segment::Placeholder(name) => match values.get(name) {
+                Some(value) => writer.write_all(value.as_bytes())?,
+                None => {
+                    return Err(TemplateError::UnknownPlaceholder {
+                        name: name.clone(),
+                        line: template.line_of(idx),
+                    }
+                    .into())
+                }
+            },
+        }
+    }
+
+    Ok(source_map)
+}
+
+/// counts the lines that have been written through it
+struct LineCounter<W> {
+    writer: W,
+    lines: usize,
+}
+
+impl<W> LineCounter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, lines: 0 }
+    }
+}
+
+impl<W: Write> Write for LineCounter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.lines += buf[..written].iter().filter(|b| **b == b'\n').count();
+        Ok(written)
     }
 
-    latex_footer(&mut writer)?;
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn write_section<W: Write>(mut writer: W, section: Section, agenda: bool) -> Result<(), Error> {
+    let mut buffer = String::with_capacity(200);
+    section.title.to_latex(&mut buffer);
+
+    // pdf bookmarks can not contain formatting
+    let plain = escape_latex(&section.title.plain_text());
+    if buffer != plain {
+        buffer = format!("\\texorpdfstring{{{}}}{{{}}}", buffer, plain);
+    }
+
+    writeln!(writer, "\\section{{{}}}\n", buffer)?;
+
+    if agenda {
+        writer.write_all(
+            r#"\begin{frame}
+    \frametitle{Outline}
+    \tableofcontents[currentsection]
+\end{frame}
+
+
+"#
+            .as_bytes(),
+        )?;
+    }
 
     Ok(())
 }
@@ -39,96 +182,170 @@ fn write_slide<W: Write>(mut writer: W, slide: Slide) -> Result<(), Error> {
 
     writer.write_all(buffer.as_bytes())?;
 
+    if !slide.notes.is_empty() {
+        let mut buffer = String::with_capacity(200);
+        nested_blocks(&slide.notes, &mut buffer);
+        write!(writer, "\n\\note{{\n{}}}\n", buffer)?;
+    }
+
     writer.write_all("\n\\end{frame}\n\n\n".as_bytes())?;
 
     Ok(())
 }
 
-fn latex_header<W: Write>(mut writer: W, title: Title, author: String) -> Result<(), Error> {
-    writer.write_all(
-        r#"
-\documentclass[aspectratio=169]{beamer}
-\usepackage{graphicx}
-\usepackage{float}
-\usepackage{hyperref}
-\usepackage{ulem}
-\usepackage{listings}
-\usepackage{xcolor}
-\usepackage{cancel}
-\usepackage{esint}
-\usepackage{gensymb}
-\usepackage{mathtools}
-\usepackage{multirow}
-
-\definecolor{codegreen}{rgb}{0,0.6,0}
-\definecolor{codegray}{rgb}{0.5,0.5,0.5}
-\definecolor{codepurple}{rgb}{0.58,0,0.82}
-\definecolor{backcolour}{rgb}{0.95,0.95,0.92}
-
-\lstdefinestyle{mystyle}{
-    backgroundcolor=\color{backcolour},
-    commentstyle=\color{codegreen},
-    keywordstyle=\color{magenta},
-    numberstyle=\tiny\color{codegray},
-    stringstyle=\color{codepurple},
-    basicstyle=\ttfamily\tiny,
-    breakatwhitespace=false,
-    breaklines=true,
-    captionpos=b,
-    keepspaces=true,
-    numbers=left,
-    numbersep=5pt,
-    showspaces=false,
-    showstringspaces=false,
-    showtabs=false,
-    tabsize=2
-}
+/// the value of every placeholder of the template other than `slides` and `preamble_extra`
+fn header_values(
+    title: Title,
+    subtitle: Option<Title>,
+    metadata: &Metadata,
+) -> HashMap<String, String> {
+    // custom keys from the front matter can be used in templates as well
+    let mut values: HashMap<String, String> = metadata
+        .custom
+        .iter()
+        .map(|(key, value)| (key.clone(), escape_latex(&value.to_string())))
+        .collect();
 
-\lstset{style=mystyle}
+    let mut buffer = String::with_capacity(200);
+    title.to_latex(&mut buffer);
+    values.insert("title".into(), buffer);
 
-\hypersetup{
-colorlinks=true,
-linkcolor=blue,
-filecolor=magenta,
-urlcolor=cyan,
-}
+    let mut buffer = String::with_capacity(200);
+    if let Some(subtitle) = subtitle {
+        subtitle.to_latex(&mut buffer);
+    }
+    values.insert(
+        "subtitle_command".into(),
+        optional_command("subtitle", &buffer),
+    );
+    values.insert("subtitle".into(), buffer);
 
-\urlstyle{same}
+    let date = match &metadata.date {
+        Some(Date::Text(date)) => escape_latex(date),
+        Some(Date::Today) | None => "\\today".to_string(),
+    };
+    values.insert("date".into(), date);
 
-\title{"#
-            .as_bytes(),
-    )?;
+    let (author, institute) = authors(metadata);
+    values.insert("author".into(), author);
+    let institute = institute.unwrap_or_default();
+    values.insert(
+        "institute_command".into(),
+        optional_command("institute", &institute),
+    );
+    values.insert("institute".into(), institute);
 
-    let mut buffer = String::with_capacity(200);
-    title.to_latex(&mut buffer);
-    writer.write_all(buffer.as_bytes())?;
+    let aspect_ratio = metadata.aspectratio.as_deref().unwrap_or("169");
+    values.insert("aspectratio".into(), aspect_ratio.to_string());
 
-    writer.write_all(
-        r#"}
-\date{\today}
-\author{"#
-            .as_bytes(),
-    )?;
+    let babel = match &metadata.lang {
+        Some(lang) => format!("\\usepackage[{}]{{babel}}\n", lang),
+        None => String::new(),
+    };
+    values.insert("babel".into(), babel);
 
-    writer.write_all(author.as_bytes())?;
+    let themes = [
+        ("usetheme", &metadata.theme),
+        ("usecolortheme", &metadata.colortheme),
+        ("usefonttheme", &metadata.fonttheme),
+        ("useinnertheme", &metadata.innertheme),
+        ("useoutertheme", &metadata.outertheme),
+    ];
 
-    writer.write_all(
-        r#"}
-\begin{document}
-\frame{\titlepage}
+    let mut theme = String::new();
+    for (command, name) in themes {
+        if let Some(name) = name {
+            writeln!(theme, "\\{}{{{}}}", command, name).unwrap();
+        }
+    }
+    values.insert("theme".into(), theme);
 
-"#
-        .as_bytes(),
-    )?;
+    let notes = match metadata.notes {
+        NotesMode::Hide => "",
+        NotesMode::Show => "\\setbeameroption{show notes}\n",
+        NotesMode::SecondScreen => {
+            "\\usepackage{pgfpages}\n\\setbeameroption{show notes on second screen=right}\n"
+        }
+    };
+    values.insert("notes".into(), notes.to_string());
 
-    Ok(())
+    let mut pdfinfo = String::new();
+    if !metadata.custom.is_empty() {
+        // custom keys end up in the document properties of the pdf
+        pdfinfo.push_str("\\hypersetup{pdfinfo={\n");
+        for (key, value) in &metadata.custom {
+            let value = escape_latex(&value.to_string());
+            writeln!(pdfinfo, "    {}={{{}}},", escape_latex(key), value).unwrap();
+        }
+        pdfinfo.push_str("}}\n");
+    }
+    values.insert("pdfinfo".into(), pdfinfo);
+
+    values
 }
 
-fn latex_footer<W: Write>(mut writer: W) -> Result<(), Error> {
-    writer.write_all(
-        r#"
-\end{document}"#
-            .as_bytes(),
-    )?;
-    Ok(())
+/// a `\command{value}` line, or nothing without a value so that beamer leaves it out of the title
+/// page
+fn optional_command(command: &str, value: &str) -> String {
+    if value.is_empty() {
+        String::new()
+    } else {
+        format!("\\{}{{{}}}\n", command, value)
+    }
+}
+
+/// text of `\author` and `\institute`. Authors with different affiliations are marked with
+/// `\inst{n}`
+fn authors(metadata: &Metadata) -> (String, Option<String>) {
+    let mut affiliations: Vec<&str> = Vec::new();
+
+    for affiliation in metadata
+        .author
+        .iter()
+        .filter_map(|a| a.affiliation.as_deref())
+    {
+        if !affiliations.contains(&affiliation) {
+            affiliations.push(affiliation);
+        }
+    }
+
+    let mark_authors = affiliations.len() > 1;
+
+    let authors = metadata
+        .author
+        .iter()
+        .map(|author| {
+            let mut name = escape_latex(&author.name);
+
+            if mark_authors {
+                if let Some(affiliation) = &author.affiliation {
+                    let number = affiliations.iter().position(|a| a == affiliation).unwrap() + 1;
+                    name.push_str(&format!("\\inst{{{}}}", number));
+                }
+            }
+
+            name
+        })
+        .collect::<Vec<_>>()
+        .join(" \\and ");
+
+    // an explicit institute replaces the one built from the affiliations
+    let institute = match &metadata.institute {
+        Some(institute) => Some(escape_latex(institute)),
+        None if mark_authors => Some(
+            affiliations
+                .iter()
+                .enumerate()
+                .map(|(idx, affiliation)| {
+                    format!("\\inst{{{}}}{}", idx + 1, escape_latex(affiliation))
+                })
+                .collect::<Vec<_>>()
+                .join(" \\and "),
+        ),
+        None => affiliations
+            .first()
+            .map(|affiliation| escape_latex(affiliation)),
+    };
+
+    (authors, institute)
 }
@@ -1,10 +1,20 @@
+mod bundle;
+mod compile;
 mod data;
+mod diagnostic;
 mod latex;
+mod metadata;
 mod parse;
+mod pictures;
 mod postprocess;
+mod template;
+#[cfg(test)]
+mod test_util;
+mod theme;
+mod watch;
 
 use argh::FromArgs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(FromArgs)]
 /// Generate presentations in latex from markdown
@@ -13,6 +23,74 @@ struct MarkdownPdfArguments {
     /// dont respect %NEWSLIDE directives
     ignore_newslide: bool,
 
+    #[argh(switch)]
+    /// compile the generated .tex into a pdf with a local tex engine
+    compile: bool,
+
+    #[argh(option, default = "compile::Engine::Pdflatex")]
+    /// engine used by --compile: pdflatex (default), xelatex, lualatex or latexmk
+    engine: compile::Engine,
+
+    #[argh(switch)]
+    /// regenerate the output every time the markdown or one of its pictures changes
+    watch: bool,
+
+    #[argh(option)]
+    /// beamer theme, overrides the front matter
+    theme: Option<String>,
+
+    #[argh(option)]
+    /// beamer color theme, overrides the front matter
+    color_theme: Option<String>,
+
+    #[argh(option)]
+    /// beamer font theme, overrides the front matter
+    font_theme: Option<String>,
+
+    #[argh(option)]
+    /// beamer inner theme, overrides the front matter
+    inner_theme: Option<String>,
+
+    #[argh(option)]
+    /// beamer outer theme, overrides the front matter
+    outer_theme: Option<String>,
+
+    #[argh(option)]
+    /// aspect ratio of the slides: 43, 169 (default), 1610, 149, 141, 54 or 32
+    aspect_ratio: Option<String>,
+
+    #[argh(switch)]
+    /// add an outline frame at the start of every `#` section
+    agenda: bool,
+
+    #[argh(option)]
+    /// how `###` headings are shown: block (default) or bold
+    subheadings: Option<metadata::SubheadingStyle>,
+
+    #[argh(option)]
+    /// speaker notes: hide (default), show, or second-screen to show them to the right of the slides
+    notes: Option<metadata::NotesMode>,
+
+    #[argh(option)]
+    /// latex template with {{title}}, {{author}}, {{slides}} and other placeholders
+    template: Option<PathBuf>,
+
+    #[argh(option)]
+    /// file with latex that is added to the preamble of the template
+    extra_preamble: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// copy every picture into an assets/ folder next to the .tex, so the output can be shared as is
+    bundle: bool,
+
+    #[argh(switch)]
+    /// like --bundle, and also write a .zip of the .tex and its assets
+    zip: bool,
+
+    #[argh(switch, short = 'v')]
+    /// print the parsed markdown to stderr
+    verbose: bool,
+
     #[argh(positional)]
     /// path/to/markdown.md
     markdown_input: PathBuf,
@@ -23,14 +101,15 @@ struct MarkdownPdfArguments {
 }
 
 fn main() {
-    if let Err(e) = wrapper() {
-        println!("An Error: {}", e)
+    let args: MarkdownPdfArguments = argh::from_env();
+
+    if let Err(e) = wrapper(args) {
+        eprintln!("error: {}", e);
+        std::process::exit(e.exit_code());
     }
 }
 
-fn wrapper() -> Result<(), Error> {
-    let mut args: MarkdownPdfArguments = argh::from_env();
-
+fn wrapper(mut args: MarkdownPdfArguments) -> Result<(), Error> {
     let file_name = args
         .markdown_input
         .file_stem()
@@ -40,16 +119,158 @@ fn wrapper() -> Result<(), Error> {
 
     args.output_directory.push(format!("{}.tex", file_name));
 
-    let f = std::fs::File::open(args.markdown_input)?;
-    let parse_results = parse::parse_markdown(f)?;
-    let processed_results = postprocess::postprocess(parse_results, args.ignore_newslide);
+    if args.watch {
+        watch::watch(args.markdown_input.clone(), || build(&args));
+    }
 
-    let out = std::fs::File::create(args.output_directory)?;
+    build(&args)?;
 
-    latex::write_latex(out, processed_results)?;
     Ok(())
 }
 
+/// generate the `.tex` file (and the pdf with `--compile`), returning every file it was built from
+fn build(args: &MarkdownPdfArguments) -> Result<Vec<PathBuf>, Error> {
+    let markdown = String::from_utf8(std::fs::read(&args.markdown_input)?)?;
+    let mut parse_results = parse::parse_markdown(markdown.as_bytes())?;
+
+    let metadata = &mut parse_results.first.metadata;
+    let overrides = [
+        (&mut metadata.theme, &args.theme),
+        (&mut metadata.colortheme, &args.color_theme),
+        (&mut metadata.fonttheme, &args.font_theme),
+        (&mut metadata.innertheme, &args.inner_theme),
+        (&mut metadata.outertheme, &args.outer_theme),
+        (&mut metadata.aspectratio, &args.aspect_ratio),
+    ];
+
+    for (setting, value) in overrides {
+        if value.is_some() {
+            setting.clone_from(value);
+        }
+    }
+
+    if args.agenda {
+        metadata.agenda = true;
+    }
+
+    if let Some(style) = args.subheadings {
+        metadata.subheadings = style;
+    }
+
+    if let Some(notes) = args.notes {
+        metadata.notes = notes;
+    }
+
+    for warning in theme::validate(metadata)? {
+        eprintln!("warning: {}", warning);
+    }
+
+    if args.verbose {
+        eprintln!("{:#?}", parse_results);
+    }
+
+    // pictures are relative to the markdown file, and are included relative to the .tex file
+    let markdown_directory = args.markdown_input.parent().unwrap_or(Path::new(""));
+    let output_directory = args.output_directory.parent().unwrap_or(Path::new(""));
+    let output_directory = if output_directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        output_directory
+    };
+
+    let pictures = pictures::resolve(
+        &mut parse_results,
+        &markdown,
+        markdown_directory,
+        output_directory,
+    )
+    .map_err(Error::Picture)?;
+
+    let assets = if args.bundle || args.zip {
+        bundle::copy_assets(&mut parse_results, &pictures, output_directory)?
+    } else {
+        Vec::new()
+    };
+
+    let mut inputs = vec![args.markdown_input.clone()];
+    inputs.extend(pictures);
+
+    let processed_results = postprocess::postprocess(parse_results, args.ignore_newslide);
+
+    let template = match &args.template {
+        Some(path) => {
+            inputs.push(path.clone());
+            template::Template::from_file(path)?
+        }
+        None => template::Template::default(),
+    };
+
+    let preamble_extra = match &args.extra_preamble {
+        Some(path) => {
+            inputs.push(path.clone());
+            std::fs::read_to_string(path)?
+        }
+        None => String::new(),
+    };
+
+    let mut latex = Vec::new();
+    let source_map = latex::write_latex(&mut latex, processed_results, &template, &preamble_extra)?;
+
+    let changed = write_if_changed(&args.output_directory, &latex)?;
+
+    if changed && args.watch {
+        eprintln!("wrote {}", args.output_directory.display());
+    }
+
+    if args.zip {
+        let tex_name = args.output_directory.file_name().unwrap().to_string_lossy();
+        bundle::write_zip(
+            &args.output_directory.with_extension("zip"),
+            &tex_name,
+            &latex,
+            &assets,
+        )?;
+    }
+
+    if args.compile && needs_compile(args.watch, changed, &args.output_directory, &inputs) {
+        compile::compile(&args.output_directory, args.engine, &source_map)?;
+    }
+
+    Ok(inputs)
+}
+
+/// a run from the command line always compiles, since the last run may have failed or the pdf may
+/// have been removed. Rebuilds while watching skip an unchanged `.tex` as long as its pdf is newer
+/// than every input, a changed picture does not change the `.tex` but it does change the pdf
+fn needs_compile(watch: bool, changed: bool, tex_path: &Path, inputs: &[PathBuf]) -> bool {
+    if !watch || changed {
+        return true;
+    }
+
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let pdf = match modified(&tex_path.with_extension("pdf")) {
+        Some(pdf) => pdf,
+        None => return true,
+    };
+
+    inputs
+        .iter()
+        .any(|input| modified(input).is_none_or(|input| input > pdf))
+}
+
+/// only touch the output when its contents differ, so that tools watching it are not
+/// triggered for nothing. returns if the file was written
+fn write_if_changed(path: &Path, contents: &[u8]) -> Result<bool, Error> {
+    if std::fs::read(path).is_ok_and(|current| current == contents) {
+        return Ok(false);
+    }
+
+    std::fs::write(path, contents)?;
+
+    Ok(true)
+}
+
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error("IoError occured: `{0}`")]
@@ -60,6 +281,76 @@ enum Error {
     BadFileName,
     #[error("The markdown file name provided was not UTF-8")]
     NonUtf8Filename,
-    #[error("Parsing Error")]
-    Nom,
+    #[error("{0}")]
+    Parse(diagnostic::Diagnostic),
+    #[error("{0}")]
+    Picture(diagnostic::Diagnostic),
+    #[error("could not write the zip archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("{0}")]
+    Compile(#[from] compile::CompileError),
+    #[error("{0}")]
+    Theme(#[from] theme::UnknownOption),
+    #[error("{0}")]
+    Template(#[from] template::TemplateError),
+}
+
+impl Error {
+    /// exit status of the process for each kind of error. argh already uses 1 for bad arguments
+    fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(_) | Error::Zip(_) => 2,
+            Error::Encoding(_) => 3,
+            Error::Parse(_) => 4,
+            Error::BadFileName | Error::NonUtf8Filename => 5,
+            Error::Compile(_) => 6,
+            Error::Theme(_) | Error::Template(_) => 7,
+            Error::Picture(_) => 8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn compile_unless_watching_an_up_to_date_pdf() {
+        let directory = TempDir::new("compile");
+
+        let tex = directory.join("talk.tex");
+        let markdown = directory.join("talk.md");
+        std::fs::write(&markdown, "").unwrap();
+        let inputs = vec![markdown.clone()];
+
+        // no pdf yet
+        assert!(needs_compile(false, false, &tex, &inputs));
+        assert!(needs_compile(true, false, &tex, &inputs));
+
+        let pdf = std::fs::File::create(tex.with_extension("pdf")).unwrap();
+        let now = SystemTime::now();
+        pdf.set_modified(now).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&markdown)
+            .unwrap()
+            .set_modified(now - Duration::from_secs(10))
+            .unwrap();
+
+        // an unchanged .tex is compiled again from the command line, but not while watching
+        assert!(needs_compile(false, false, &tex, &inputs));
+        assert!(!needs_compile(true, false, &tex, &inputs));
+        assert!(needs_compile(true, true, &tex, &inputs));
+
+        // a picture saved after the pdf was built
+        let picture = directory.join("plot.png");
+        std::fs::File::create(&picture)
+            .unwrap()
+            .set_modified(now + Duration::from_secs(10))
+            .unwrap();
+        assert!(needs_compile(true, false, &tex, &[markdown, picture]));
+    }
 }
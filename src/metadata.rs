@@ -0,0 +1,386 @@
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// information about the presentation from the front matter at the start of the markdown
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Metadata {
+    pub(crate) title: Option<String>,
+    pub(crate) subtitle: Option<String>,
+    #[serde(alias = "authors", deserialize_with = "one_or_many")]
+    pub(crate) author: Vec<Author>,
+    pub(crate) institute: Option<String>,
+    pub(crate) date: Option<Date>,
+    pub(crate) theme: Option<String>,
+    #[serde(alias = "color_theme", alias = "color-theme")]
+    pub(crate) colortheme: Option<String>,
+    #[serde(alias = "font_theme", alias = "font-theme")]
+    pub(crate) fonttheme: Option<String>,
+    #[serde(alias = "inner_theme", alias = "inner-theme")]
+    pub(crate) innertheme: Option<String>,
+    #[serde(alias = "outer_theme", alias = "outer-theme")]
+    pub(crate) outertheme: Option<String>,
+    #[serde(
+        alias = "aspect_ratio",
+        alias = "aspect-ratio",
+        deserialize_with = "optional_text"
+    )]
+    pub(crate) aspectratio: Option<String>,
+    #[serde(alias = "language")]
+    pub(crate) lang: Option<String>,
+    /// an outline frame at the start of every section
+    pub(crate) agenda: bool,
+    pub(crate) subheadings: SubheadingStyle,
+    pub(crate) notes: NotesMode,
+    /// every other key, added to the information dictionary of the pdf
+    #[serde(flatten)]
+    pub(crate) custom: BTreeMap<String, Value>,
+}
+
+/// how `###` headings inside of a slide are shown
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SubheadingStyle {
+    /// a beamer `block` holding everything up to the next `###`
+    #[default]
+    Block,
+    /// a bold line of text
+    Bold,
+}
+
+impl FromStr for SubheadingStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(SubheadingStyle::Block),
+            "bold" => Ok(SubheadingStyle::Bold),
+            other => Err(format!(
+                "unknown subheading style `{other}`, expected block or bold"
+            )),
+        }
+    }
+}
+
+/// where the speaker notes of the slides are shown
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum NotesMode {
+    /// only the slides are in the pdf
+    #[default]
+    Hide,
+    /// a page with the notes after every slide
+    Show,
+    /// the notes to the right of every slide, for a presenter console on a second screen
+    SecondScreen,
+}
+
+impl FromStr for NotesMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hide" => Ok(NotesMode::Hide),
+            "show" => Ok(NotesMode::Show),
+            "second-screen" => Ok(NotesMode::SecondScreen),
+            other => Err(format!(
+                "unknown notes mode `{other}`, expected hide, show or second-screen"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(from = "AuthorEntry")]
+pub(crate) struct Author {
+    pub(crate) name: String,
+    pub(crate) affiliation: Option<String>,
+}
+
+impl Author {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            affiliation: None,
+        }
+    }
+}
+
+/// an author is either just a name or a table with a name and an affiliation
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AuthorEntry {
+    Name(String),
+    Detailed {
+        name: String,
+        #[serde(default, alias = "institute")]
+        affiliation: Option<String>,
+    },
+}
+
+impl From<AuthorEntry> for Author {
+    fn from(entry: AuthorEntry) -> Self {
+        match entry {
+            AuthorEntry::Name(name) => Author::new(name),
+            AuthorEntry::Detailed { name, affiliation } => Author { name, affiliation },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Date {
+    /// the day the presentation is compiled
+    Today,
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = Value::deserialize(deserializer)?.to_string();
+
+        if text.eq_ignore_ascii_case("today") {
+            Ok(Date::Today)
+        } else {
+            Ok(Date::Text(text))
+        }
+    }
+}
+
+/// a value of a custom key
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<Value>),
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "text, a number, a boolean, a date or a list")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Integer(value))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Value, E> {
+        Ok(i64::try_from(value).map_or(Value::Float(value as f64), Value::Integer))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::Text(value.to_string()))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(Value::List(values))
+    }
+
+    // toml hands its dates and times to serde as a table with a single private key, every other
+    // table is a mistake
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Value, A::Error> {
+        let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+
+        match toml::value::Datetime::deserialize(deserializer) {
+            Ok(datetime) => Ok(Value::Text(datetime.to_string())),
+            Err(_) => Err(serde::de::Error::custom(
+                "nested tables are not supported, use text, a number or a list instead",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Text(value) => write!(f, "{value}"),
+            Value::List(values) => {
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Author>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Author),
+        Many(Vec<Author>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(author) => vec![author],
+        OneOrMany::Many(authors) => authors,
+    })
+}
+
+/// accept numbers as well as strings, `aspectratio: 169` is a number in yaml
+fn optional_text<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Some(Value::deserialize(deserializer)?.to_string()))
+}
+
+/// front matter could not be read. `offset` is the byte offset into the front matter
+#[derive(Debug)]
+pub(crate) struct MetadataError {
+    pub(crate) message: String,
+    pub(crate) offset: usize,
+}
+
+/// read yaml front matter, delimited by `---` lines
+pub(crate) fn from_yaml(text: &str) -> Result<Metadata, MetadataError> {
+    // an empty document is `null` in yaml instead of an empty mapping
+    if text.trim().is_empty() {
+        return Ok(Metadata::default());
+    }
+
+    serde_yaml::from_str(text).map_err(|e| MetadataError {
+        offset: e.location().map_or(0, |location| location.index()),
+        message: strip_location(e.to_string()),
+    })
+}
+
+/// read toml front matter, delimited by `+++` lines
+pub(crate) fn from_toml(text: &str) -> Result<Metadata, MetadataError> {
+    toml::from_str(text).map_err(|e| MetadataError {
+        offset: e.span().map_or(0, |span| span.start),
+        message: e.message().to_string(),
+    })
+}
+
+/// serde_yaml adds ` at line 2 column 3` to its messages, which the diagnostic already shows
+fn strip_location(message: String) -> String {
+    match message.find(" at line ") {
+        Some(idx) => message[..idx].to_string(),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_front_matter() {
+        let text = r#"
+title: A **bold** talk
+subtitle: with a subtitle
+authors:
+  - name: First Author
+    affiliation: University
+  - Second Author
+date: today
+aspectratio: 43
+language: ngerman
+event: Conference 2024
+"#;
+
+        let metadata = from_yaml(text).unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("A **bold** talk"));
+        assert_eq!(metadata.subtitle.as_deref(), Some("with a subtitle"));
+        assert_eq!(
+            metadata.author,
+            vec![
+                Author {
+                    name: "First Author".into(),
+                    affiliation: Some("University".into()),
+                },
+                Author::new("Second Author".into()),
+            ]
+        );
+        assert_eq!(metadata.date, Some(Date::Today));
+        assert_eq!(metadata.aspectratio.as_deref(), Some("43"));
+        assert_eq!(metadata.lang.as_deref(), Some("ngerman"));
+        assert_eq!(
+            metadata.custom.get("event"),
+            Some(&Value::Text("Conference 2024".into()))
+        );
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        let text = r#"
+title = "Title"
+author = "Single Author"
+institute = "Institute"
+date = "1 January 2024"
+"#;
+
+        let metadata = from_toml(text).unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.author, vec![Author::new("Single Author".into())]);
+        assert_eq!(metadata.institute.as_deref(), Some("Institute"));
+        assert_eq!(metadata.date, Some(Date::Text("1 January 2024".into())));
+        assert!(metadata.custom.is_empty());
+    }
+
+    #[test]
+    fn toml_dates_and_tables() {
+        let text = "date = 2024-01-01\nrecorded = 2024-01-02T10:00:00Z\n";
+        let metadata = from_toml(text).unwrap();
+
+        assert_eq!(metadata.date, Some(Date::Text("2024-01-01".into())));
+        assert_eq!(
+            metadata.custom.get("recorded"),
+            Some(&Value::Text("2024-01-02T10:00:00Z".into()))
+        );
+
+        let text = "title = \"Title\"\nevent = { name = \"Conference\" }\n";
+        let error = from_toml(text).unwrap_err();
+
+        assert!(error.message.contains("nested tables are not supported"));
+
+        let error = from_yaml("event:\n  name: Conference\n").unwrap_err();
+        assert!(error.message.contains("nested tables are not supported"));
+    }
+
+    #[test]
+    fn invalid_front_matter() {
+        let text = "title: Title\nauthor: [unclosed\n";
+        let error = from_yaml(text).unwrap_err();
+
+        assert!(error.offset > 0);
+        assert!(!error.message.contains(" at line "));
+    }
+}
@@ -1,32 +1,81 @@
-use super::data::Code;
+use super::data::{Alignment, Code, Table, SPLIT_GAP};
+use super::diagnostic::{Diagnostic, ParseError, ParseErrorKind};
+use super::metadata::{self, Author, Metadata};
 use super::Error;
 use std::cmp::Ordering;
 use std::io::Read;
 
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_till, take_until};
-use nom::multi::{many0, many1};
+use nom::bytes::complete::{tag, take_till, take_until, take_while1, take_while_m_n};
+use nom::character::complete::space0;
+use nom::combinator::{eof, map, opt, peek};
+use nom::multi::many0;
 use nom::sequence::tuple;
-use nom::IResult;
 
-type NomErr<'a> = nom::Err<nom::error::Error<&'a str>>;
+type IResult<I, O> = nom::IResult<I, O, ParseError<I>>;
+type NomErr<'a> = nom::Err<ParseError<&'a str>>;
+
+/// pandoc style attribute marking a code span or fenced code block as raw latex
+const RAW_LATEX_ATTRIBUTE: &str = "{=latex}";
 
 #[derive(Debug)]
 pub(crate) struct Document {
     pub(crate) first: ParsedTitle,
     pub(crate) slides: Vec<ParsedSlide>,
+    pub(crate) sections: Vec<ParsedSection>,
+}
+
+impl Document {
+    /// every picture in the presentation, including the ones in notes, blocks and quotes
+    pub(crate) fn pictures_mut(&mut self) -> Vec<&mut ParsePicture> {
+        let mut pictures = Vec::new();
+
+        for slide in self.slides.iter_mut() {
+            push_pictures(&mut slide.contents, &mut pictures);
+            push_pictures(&mut slide.notes, &mut pictures);
+        }
+
+        pictures
+    }
+}
+
+fn push_pictures<'a>(blocks: &'a mut [Block], pictures: &mut Vec<&'a mut ParsePicture>) {
+    for block in blocks {
+        match block {
+            Block::Picture(picture) => pictures.push(picture),
+            Block::BeamerBlock(group) => push_pictures(&mut group.contents, pictures),
+            Block::Quote(blocks) => push_pictures(blocks, pictures),
+            _ => (),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct ParsedTitle {
     pub(crate) title: Vec<Span>,
-    pub(crate) author: String,
+    pub(crate) subtitle: Option<Vec<Span>>,
+    pub(crate) metadata: Metadata,
 }
 
 #[derive(Debug)]
 pub(crate) struct ParsedSlide {
     pub(crate) title: Vec<Span>,
     pub(crate) contents: Vec<Block>,
+    // speaker notes
+    pub(crate) notes: Vec<Block>,
+}
+
+/// a `# Section` heading after the title of the presentation
+#[derive(Debug, PartialEq)]
+pub(crate) struct ParsedSection {
+    pub(crate) title: Vec<Span>,
+    // index of the first slide after the heading in `Document::slides`
+    pub(crate) slide: usize,
+}
+
+enum DocumentItem {
+    Section(Vec<Span>),
+    Slide(ParsedSlide),
 }
 
 pub(crate) fn parse_markdown<R: Read>(mut reader: R) -> Result<Document, Error> {
@@ -34,53 +83,298 @@ pub(crate) fn parse_markdown<R: Read>(mut reader: R) -> Result<Document, Error>
     reader.read_to_end(&mut buffer)?;
     let text = String::from_utf8(buffer)?;
 
-    let (_, presentation) = inner_parse(&text).map_err(|_| Error::Nom)?;
-    dbg!(&presentation);
+    let (_, presentation) = inner_parse(&text).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => Error::Parse(Diagnostic::new(&text, e)),
+        nom::Err::Incomplete(_) => unreachable!("only complete parsers are used"),
+    })?;
+
     Ok(presentation)
 }
 
 fn inner_parse(i: &'_ str) -> IResult<&'_ str, Document> {
     let (rest, first) = parse_start_header(i)?;
 
-    let (rest, slides) = many0(parse_slide)(rest)?;
+    let (rest, items) = many0(parse_document_item)(rest)?;
+
+    // many0 stops silently at the first slide that fails to parse, so parse it again
+    // to report why instead of dropping the rest of the presentation
+    if !rest.trim().is_empty() {
+        parse_document_item(rest)?;
+    }
+
+    let mut slides = Vec::new();
+    let mut sections = Vec::new();
+
+    for item in items {
+        match item {
+            DocumentItem::Section(title) => sections.push(ParsedSection {
+                title,
+                slide: slides.len(),
+            }),
+            DocumentItem::Slide(slide) => slides.push(slide),
+        }
+    }
+
+    Ok((
+        rest,
+        Document {
+            first,
+            slides,
+            sections,
+        },
+    ))
+}
+
+fn parse_document_item(i: &'_ str) -> IResult<&'_ str, DocumentItem> {
+    alt((
+        map(parse_section, DocumentItem::Section),
+        map(parse_slide, DocumentItem::Slide),
+    ))(i)
+}
+
+fn parse_section(i: &'_ str) -> IResult<&'_ str, Vec<Span>> {
+    let (rest, _) = take_till(|c| c != ' ' && c != '\n' && c != '\t')(i)?;
+    let (title_start, _) = tag("# ")(rest)?;
+    let (rest, title) = take_till(|c| c == '\n')(title_start)?;
 
-    Ok((rest, Document { first, slides }))
+    Ok((rest, parse_string(title)?))
 }
 
 fn parse_start_header(i: &'_ str) -> IResult<&'_ str, ParsedTitle> {
-    let (rest, _) = take_till(|c| c == '#')(i)?;
-    let (rest, _) = tag("# ")(rest)?;
-    let (rest, title_name) = take_till(|c| c == '\n')(rest)?;
-    let (author_start, _whitespace) = tag("\nAUTHOR=")(rest)?;
-    let (rest, author_name) = take_till(|c| c == '\n')(author_start)?;
+    let (rest, front_matter) = parse_front_matter(i)?;
+    let has_front_matter = front_matter.is_some();
+    let mut metadata = front_matter.unwrap_or_default();
 
-    let title_spans = parse_string(title_name)?;
+    let (title_line, _) = take_till(|c| c == '#')(rest)?;
+    let header: IResult<&str, &str> = tag("# ")(title_line);
+
+    let (rest, title_name) = match header {
+        Ok((title_start, _)) => {
+            let (rest, title_name) = take_till(|c| c == '\n')(title_start)?;
+            let author: IResult<&str, &str> = tag("\nAUTHOR=")(rest);
+
+            match author {
+                Ok((author_start, _)) => {
+                    let (rest, author_name) = take_till(|c| c == '\n')(author_start)?;
+
+                    // authors in the front matter take precedence
+                    if metadata.author.is_empty() {
+                        metadata.author.push(Author::new(author_name.to_string()));
+                    }
+
+                    (rest, Some(title_name))
+                }
+                Err(_) if has_front_matter => (rest, Some(title_name)),
+                Err(_) => return Err(ParseError::failure(rest, ParseErrorKind::MissingAuthor)),
+            }
+        }
+        // the title can come from the front matter alone
+        Err(_) if metadata.title.is_some() => (rest, None),
+        Err(_) => {
+            return Err(ParseError::failure(
+                title_line,
+                ParseErrorKind::MissingTitle,
+            ))
+        }
+    };
+
+    // the title in the front matter takes precedence
+    let title = match metadata.title.take() {
+        Some(title) => parse_front_matter_text(i, &title)?,
+        None => parse_string(title_name.unwrap_or_default())?,
+    };
+
+    let subtitle = match metadata.subtitle.take() {
+        Some(subtitle) => Some(parse_front_matter_text(i, &subtitle)?),
+        None => None,
+    };
 
     Ok((
         rest,
         ParsedTitle {
-            title: title_spans,
-            author: author_name.to_string(),
+            title,
+            subtitle,
+            metadata,
         },
     ))
 }
 
+/// optional yaml (`---`) or toml (`+++`) block at the very start of the markdown
+fn parse_front_matter(i: &'_ str) -> IResult<&'_ str, Option<Metadata>> {
+    let (start, _) = take_till(|c: char| !c.is_whitespace())(i)?;
+    let opening: IResult<&str, (&str, &str, &str)> =
+        tuple((alt((tag("---"), tag("+++"))), space0, tag("\n")))(start);
+
+    let (body, delimiter) = match opening {
+        Ok((body, (delimiter, _, _))) => (body, delimiter),
+        Err(_) => return Ok((i, None)),
+    };
+
+    // yaml documents may also be closed with `...`
+    let is_closing = |line: &str| {
+        let line = line.trim_end();
+        line == delimiter || (delimiter == "---" && line == "...")
+    };
+
+    let mut offset = 0;
+    let mut end = None;
+
+    for line in body.split_inclusive('\n') {
+        if is_closing(line) {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+
+        offset += line.len();
+    }
+
+    let (text_end, rest_start) = match end {
+        Some(end) => end,
+        None => {
+            let kind = if delimiter == "---" {
+                ParseErrorKind::UnterminatedFrontMatter("---")
+            } else {
+                ParseErrorKind::UnterminatedFrontMatter("+++")
+            };
+            return Err(ParseError::failure(start, kind));
+        }
+    };
+
+    let text = &body[..text_end];
+
+    let metadata = if delimiter == "---" {
+        metadata::from_yaml(text)
+    } else {
+        metadata::from_toml(text)
+    };
+
+    let metadata = metadata.map_err(|e| {
+        let location = text.get(e.offset..).unwrap_or(text);
+        ParseError::failure(location, ParseErrorKind::FrontMatter(e.message))
+    })?;
+
+    Ok((&body[rest_start..], Some(metadata)))
+}
+
+/// formatted text from a value of the front matter. Errors are reported at the start of the
+/// markdown since the value is no longer part of it
+fn parse_front_matter_text<'a>(i: &'a str, text: &str) -> Result<Vec<Span>, NomErr<'a>> {
+    parse_string(text).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            ParseError::failure(i, ParseErrorKind::FrontMatter(e.kind.to_string()))
+        }
+        nom::Err::Incomplete(_) => unreachable!("only complete parsers are used"),
+    })
+}
+
 fn parse_slide(i: &'_ str) -> IResult<&'_ str, ParsedSlide> {
-    let (title_start, _) = tuple((take_until("##"), tag("## ")))(i)?;
+    let (title_start, (before, _)) = tuple((take_until_slide_header, tag("## ")))(i)?;
+
+    // text between a title or section and the next slide would be lost, along with any
+    // `# Section` after it
+    let stray = before.trim_start();
+    if !stray.is_empty() {
+        return Err(ParseError::failure(stray, ParseErrorKind::TextOutsideSlide));
+    }
 
     let (rest, slide_title) = take_till(|c| c == '\n')(title_start)?;
-    let (rest, blocks) = parse_block(rest)?;
+    let (rest, blocks) = parse_located_blocks(rest)?;
+
+    check_columns(&blocks)?;
+
+    let mut contents = Vec::new();
+    let mut notes = Vec::new();
+
+    for (_, block) in blocks {
+        match block {
+            Block::Notes(blocks) => notes.extend(blocks),
+            block => contents.push(block),
+        }
+    }
 
     let slide = ParsedSlide {
         title: parse_string(slide_title)?,
-        contents: blocks,
+        contents,
+        notes,
     };
 
     Ok((rest, slide))
 }
 
+/// everything before the next line that starts with exactly `## `, so that `###` subheadings
+/// are not mistaken for slides
+fn take_until_slide_header(i: &str) -> IResult<&str, &str> {
+    let mut offset = 0;
+
+    for line in i.split_inclusive('\n') {
+        let header = line.trim_start_matches([' ', '\t']);
+
+        if header.starts_with("## ") {
+            let start = offset + line.len() - header.len();
+            return Ok((&i[start..], &i[..start]));
+        }
+
+        offset += line.len();
+    }
+
+    Err(nom::Err::Error(ParseError::new(
+        i,
+        nom::error::ErrorKind::TakeUntil,
+    )))
+}
+
+/// `%COLUMNS` must be closed by an `%ENDCOLUMNS` before the slide ends or is split by
+/// `%NEWSLIDE`, and `%COLUMN` and `%ENDCOLUMNS` only make sense inside of columns
+fn check_columns<'a>(blocks: &[(&'a str, Block)]) -> Result<(), NomErr<'a>> {
+    // the start of the `%COLUMNS` that is not closed yet
+    let mut open: Option<&str> = None;
+
+    for (start, block) in blocks {
+        match (block, open) {
+            (Block::Directive(Directive::Columns { .. }), Some(columns))
+            | (Block::Directive(Directive::NewSlide), Some(columns)) => {
+                return Err(ParseError::failure(
+                    columns,
+                    ParseErrorKind::UnclosedColumns,
+                ))
+            }
+            (Block::Directive(Directive::Columns { .. }), None) => open = Some(start),
+            (Block::Directive(Directive::Column), None) => {
+                return Err(ParseError::failure(
+                    start,
+                    ParseErrorKind::StrayColumnDirective("%COLUMN"),
+                ))
+            }
+            (Block::Directive(Directive::EndColumns), None) => {
+                return Err(ParseError::failure(
+                    start,
+                    ParseErrorKind::StrayColumnDirective("%ENDCOLUMNS"),
+                ))
+            }
+            (Block::Directive(Directive::EndColumns), Some(_)) => open = None,
+            _ => (),
+        }
+    }
+
+    match open {
+        Some(columns) => Err(ParseError::failure(
+            columns,
+            ParseErrorKind::UnclosedColumns,
+        )),
+        None => Ok(()),
+    }
+}
+
 // TODO: stop conditions for pictures
-fn parse_block<'a>(i: &'a str) -> IResult<&'a str, Vec<Block>> {
+fn parse_block(i: &str) -> IResult<&str, Vec<Block>> {
+    let (rest, blocks) = parse_located_blocks(i)?;
+    let blocks = blocks.into_iter().map(|x| x.1).collect();
+
+    Ok((rest, blocks))
+}
+
+/// the blocks of a slide, each with the input that it starts at
+fn parse_located_blocks(i: &str) -> IResult<&str, Vec<(&str, Block)>> {
     let whitespace = take_till(|c| c != ' ' && c != '\n' && c != '\t');
 
     let end_of_slide = tuple((whitespace, alt((nom::combinator::eof, is_start_header))));
@@ -91,23 +385,33 @@ fn parse_block<'a>(i: &'a str) -> IResult<&'a str, Vec<Block>> {
         tuple((
             whitespace,
             alt((
+                parse_as_notes,
                 parse_as_directive,
+                parse_as_link_definition,
+                parse_as_heading,
+                parse_as_quote,
                 parse_block_as_picture,
                 parse_block_as_bullets,
                 parse_as_code,
+                parse_block_as_equation,
+                parse_block_as_table,
                 parse_block_as_paragraph,
             )),
         )),
         end_of_slide,
     )(i)?;
 
-    let blocks = blocks.into_iter().map(|x| x.1).collect();
+    // the whitespace before a block ends where the block starts
+    let blocks = blocks
+        .into_iter()
+        .map(|(whitespace, block)| (&whitespace[whitespace.len()..], block))
+        .collect();
 
     Ok((rest, blocks))
 }
 
 fn is_start_header(i: &str) -> IResult<&str, &str> {
-    match tag("##")(i) {
+    match alt((tag("## "), tag("# ")))(i) {
         Ok(_) => Ok((i, "")),
         Err(e) => Err(e),
     }
@@ -127,10 +431,8 @@ fn parse_block_as_picture(i: &str) -> IResult<&str, Block> {
     let (rest, location) = take_till(|c| c == ')')(location_start)?;
     let (mut rest, _) = tag(")")(rest)?;
 
-    println!("searching for picture directives");
-
     let directive = if let Ok((new_rest, directive)) = many0(picture_directive)(rest) {
-        if directive.len() == 0 {
+        if directive.is_empty() {
             None
         } else {
             rest = new_rest;
@@ -140,7 +442,7 @@ fn parse_block_as_picture(i: &str) -> IResult<&str, Block> {
         None
     };
 
-    let caption = if caption.len() == 0 {
+    let caption = if caption.is_empty() {
         None
     } else {
         Some(caption.to_string())
@@ -180,26 +482,81 @@ fn picture_directive(i: &str) -> IResult<&str, PictureDirective> {
         Ok((rest, PictureDirective::Height(width_query.to_string())))
     };
 
-    let (rest, directive) = alt((vertical, width, height))(after_whitespace)?;
+    let side = alt((
+        map(tag("%LEFT"), |_| PictureDirective::Left),
+        map(tag("%RIGHT"), |_| PictureDirective::Right),
+        map(tag("%TOP"), |_| PictureDirective::Top),
+    ));
+
+    let (rest, directive) = alt((vertical, width, height, side, split))(after_whitespace)?;
 
     Ok((rest, directive))
 }
 
+/// `%SPLIT=0.3` gives the text 0.3 of the width of the slide and the picture the rest, and
+/// `%SPLIT=0.3,0.6` sets the width of both
+fn split(i: &str) -> IResult<&str, PictureDirective> {
+    let (rest, _) = tag("%SPLIT=")(i)?;
+    let (rest, ratios) = take_till(|c| c == '\n')(rest)?;
+
+    let invalid = || ParseError::failure(ratios, ParseErrorKind::InvalidSplit(ratios.to_string()));
+
+    let parsed = ratios
+        .split(',')
+        .map(|ratio| ratio.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+
+    let (text, picture) = match parsed[..] {
+        [text] => (text, 1.0 - SPLIT_GAP - text),
+        [text, picture] => (text, picture),
+        _ => return Err(invalid()),
+    };
+
+    // `NaN` and `inf` parse as numbers, but every comparison with `NaN` is false
+    if !text.is_finite() || !picture.is_finite() {
+        return Err(invalid());
+    }
+
+    if text <= 0.0 || picture <= 0.0 || text + picture > 1.0 {
+        return Err(invalid());
+    }
+
+    Ok((rest, PictureDirective::Split { text, picture }))
+}
+
 fn parse_block_as_bullets(i: &str) -> IResult<&str, Block> {
     let take_whitespace = take_till(|c| c != '\n');
 
-    let (rest, bullets) = many1(tuple((take_whitespace, parse_bullet_item)))(i)?;
+    let (rest, (_, first)) = tuple((&take_whitespace, parse_bullet_item))(i)?;
+    let top_indentation = first.0;
+    let kind = first.1.clone();
+
+    // like in commonmark, changing between numbers and bullets starts a new list
+    let same_list = |i| {
+        let (rest, (_, bullet)) = tuple((&take_whitespace, parse_bullet_item))(i)?;
+
+        if bullet.0 == top_indentation && !kind.same_kind(&bullet.1) {
+            return Err(nom::Err::Error(ParseError::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            )));
+        }
+
+        Ok((rest, bullet))
+    };
 
-    let mut bullets: Vec<(usize, BulletItem)> = bullets.into_iter().map(|x| x.1).collect();
+    let (rest, more) = many0(same_list)(rest)?;
 
-    let mut organized = Vec::new();
+    let mut bullets: Vec<(usize, ListKind, BulletItem)> = vec![first];
+    bullets.extend(more);
 
-    collect_bullet_items(&mut bullets, &mut organized, 0);
+    let organized = collect_bullet_items(&mut bullets, 0);
 
     Ok((rest, Block::BulletedList(organized)))
 }
 
-fn parse_bullet_item(i: &str) -> IResult<&str, (usize, BulletItem)> {
+fn parse_bullet_item(i: &str) -> IResult<&str, (usize, ListKind, BulletItem)> {
     let (rest, taken) = take_till(|c| c != ' ' && c != '\t')(i)?;
 
     let current_indentation = taken.chars().fold(0, |acc, x| {
@@ -212,209 +569,1189 @@ fn parse_bullet_item(i: &str) -> IResult<&str, (usize, BulletItem)> {
         }
     }) / 4;
 
-    let (rest, (_, bullet_text)) =
-        tuple((tag("* "), alt((take_until("\n"), nom::combinator::rest))))(rest)?;
+    let (rest, (kind, bullet_text)) =
+        tuple((list_marker, alt((take_until("\n"), nom::combinator::rest))))(rest)?;
 
     Ok((
         rest,
         (
             current_indentation,
+            kind,
             BulletItem::Single(parse_string(bullet_text)?),
         ),
     ))
 }
 
+/// parse the marker at the start of a list item (`* `, `- `, `+ `, `1. ` or `1) `)
+fn list_marker(i: &str) -> IResult<&str, ListKind> {
+    let unordered = |i| -> IResult<&str, ListKind> {
+        let (rest, _) = alt((tag("* "), tag("- "), tag("+ ")))(i)?;
+        Ok((rest, ListKind::Unordered))
+    };
+
+    let ordered = |i| -> IResult<&str, ListKind> {
+        // commonmark limits ordered list numbers to 9 digits, which also means they always
+        // fit in a usize
+        let (rest, (number, _, _)) = tuple((
+            take_while_m_n(1, 9, |c: char| c.is_ascii_digit()),
+            alt((tag("."), tag(")"))),
+            tag(" "),
+        ))(i)?;
+
+        let start = number.parse().unwrap();
+
+        Ok((rest, ListKind::Ordered { start }))
+    };
+
+    alt((unordered, ordered))(i)
+}
+
 fn parse_as_code(i: &str) -> IResult<&str, Block> {
-    let (rest, _whitespace) = take_till(|c| c != '\n')(i)?;
-    let (code_internal, header) = code_block_header(rest)?;
+    let (fence, _whitespace) = take_till(|c| c != '\n')(i)?;
+    let (code_internal, header) = code_block_header(fence)?;
 
-    let (rest, code) = take_until_parser_success(code_internal, tag("```"))?;
+    let (rest, code) = take_until("```")(code_internal)
+        .map_err(|_: NomErr| ParseError::failure(fence, ParseErrorKind::UnterminatedCodeBlock))?;
     let (rest, _code_end) = tag("```")(rest)?;
 
+    // fenced blocks marked with `{=latex}` are copied to the output without any changes
+    if header.language == RAW_LATEX_ATTRIBUTE {
+        return Ok((rest, Block::Latex(code.to_string())));
+    }
+
     Ok((
         rest,
         Block::Code(Code::new(code.to_string(), header.language)),
     ))
 }
 
-fn parse_as_directive(i: &str) -> IResult<&str, Block> {
+/// an equation with `$$` or `\[` and `\]` on their own lines
+fn parse_block_as_equation(i: &str) -> IResult<&str, Block> {
+    let (start, _whitespace) = take_till(|c| c != '\n')(i)?;
+
+    let (rest, opening) = alt((tag("$$"), tag("\\[")))(start)?;
+    let closing = if opening == "$$" { "$$" } else { "\\]" };
+
+    let (rest, _) = tuple((space0, tag("\n")))(rest)?;
+    let (rest, equation) = take_until(closing)(rest).map_err(|_: NomErr| {
+        ParseError::failure(start, ParseErrorKind::UnterminatedEquation(closing))
+    })?;
+    let (rest, _) = tag(closing)(rest)?;
+
+    // nothing else is allowed on the line of the closing delimiter
+    let (rest, _) = peek(tuple((space0, alt((tag("\n"), eof)))))(rest)?;
+
+    Ok((rest, Block::Equation(equation.trim_end().to_string())))
+}
+
+fn parse_block_as_table(i: &str) -> IResult<&str, Block> {
     let (rest, _whitespace) = take_till(|c| c != '\n')(i)?;
 
-    let (rest, _newslide_header) = tag("%NEWSLIDE")(rest)?;
+    let pipes = outer_pipes(rest);
+    let (rest, header) = table_row(rest)?;
+    let (rest, alignment) = table_delimiter_row(rest)?;
+
+    // the delimiter row must have a column for every header cell
+    if alignment.len() != header.len() {
+        return Err(nom::Err::Error(ParseError::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+
+    // the table ends at the first line that is not written like the header, so that a paragraph
+    // directly after it can still contain a `|`
+    let body_row = |i| {
+        if outer_pipes(i) != pipes {
+            return Err(nom::Err::Error(ParseError::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            )));
+        }
+
+        table_row(i)
+    };
 
-    Ok((rest, Block::Directive(Directive::NewSlide)))
+    let (rest, rows) = many0(body_row)(rest)?;
+
+    let header = header
+        .into_iter()
+        .map(parse_table_cell)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rows = rows
+        .into_iter()
+        .map(|mut row| {
+            // rows with the wrong number of cells are padded or truncated to match the header
+            row.resize(alignment.len(), "");
+            row.into_iter()
+                .map(parse_table_cell)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((rest, Block::Table(Table::new(header, alignment, rows))))
 }
 
-#[derive(Debug)]
-struct CodeHeader {
-    language: String,
+/// a single line of a table, split into its (untrimmed) cells
+fn table_row(i: &str) -> IResult<&str, Vec<&str>> {
+    let (rest, line) = take_till(|c| c == '\n')(i)?;
+    let (rest, _newline) = alt((tag("\n"), nom::combinator::eof))(rest)?;
+
+    if !line.contains('|') {
+        return Err(nom::Err::Error(ParseError::new(
+            i,
+            nom::error::ErrorKind::Char,
+        )));
+    }
+
+    Ok((rest, split_table_cells(line)))
 }
 
-fn code_block_header(i: &str) -> IResult<&str, CodeHeader> {
-    let (after_ticks, _ticks) = tag("```")(i)?;
-    let (rest, language_name) = take_till(|c| c == '\n')(after_ticks)?;
-    let (code_start, _newline) = tag("\n")(rest)?;
+/// whether the next line starts and ends with a `|`
+fn outer_pipes(i: &str) -> (bool, bool) {
+    let line = i.split('\n').next().unwrap_or_default().trim();
 
-    Ok((
-        code_start,
-        CodeHeader {
-            language: language_name.to_string(),
-        },
-    ))
+    (
+        line.starts_with('|'),
+        line.ends_with('|') && !line.ends_with("\\|"),
+    )
 }
 
-fn collect_bullet_items(
-    flat: &mut Vec<(usize, BulletItem)>,
-    nested: &mut Vec<BulletItem>,
-    current_indentation: usize,
-) {
-    loop {
-        if !flat.is_empty() {
-            let (indentation, _bullet_data) = &flat[0];
-            // copy the data for borrowing rules
-            let indentation = *indentation;
-
-            match indentation.cmp(&current_indentation) {
-                Ordering::Greater => {
-                    let mut new_buffer = Vec::new();
-                    collect_bullet_items(flat, &mut new_buffer, indentation);
-                    nested.push(BulletItem::Nested(new_buffer));
-                }
-                Ordering::Less => {
-                    // return back to the previous level of indentation
-                    return;
-                }
-                Ordering::Equal => {
-                    // we have the same level of indentation
-                    let item = flat.remove(0);
-                    nested.push(item.1)
-                }
+/// the `| --- | :---: | ---: |` row that separates the header from the body of a table
+fn table_delimiter_row(i: &str) -> IResult<&str, Vec<Alignment>> {
+    let (rest, cells) = table_row(i)?;
+
+    let alignment = cells
+        .into_iter()
+        .map(|cell| {
+            let cell = cell.trim();
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return Err(nom::Err::Error(ParseError::new(
+                    i,
+                    nom::error::ErrorKind::Verify,
+                )));
             }
-        } else {
-            break;
-        }
-    }
+
+            let alignment = match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
+            };
+
+            Ok(alignment)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((rest, alignment))
 }
 
-fn parse_string<'a>(i: &'a str) -> Result<Vec<Span>, NomErr> {
-    let span_options = |x: &'a str| {
-        alt((
-            parse_strikethrough,
-            parse_bold,
-            parse_italics,
-            parse_equation,
-            parse_regular_text,
-        ))(x)
+fn split_table_cells(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = if line.ends_with('|') && !line.ends_with("\\|") {
+        &line[..line.len() - 1]
+    } else {
+        line
     };
 
-    let out = nom::multi::many1(span_options)(i)?;
+    let mut cells = Vec::new();
+    let mut cell_start = 0;
+    let mut previous = None;
 
-    Ok(out.1)
-}
+    for (idx, c) in line.char_indices() {
+        // `\|` is a literal pipe inside of a cell
+        if c == '|' && previous != Some('\\') {
+            cells.push(&line[cell_start..idx]);
+            cell_start = idx + 1;
+        }
 
-// TODO: does not handle escaped sequences
-fn parse_bold(i: &str) -> IResult<&str, Span> {
-    let (rest, (_, bolded_text, _)) = tuple((
-        //
-        tag("**"),
-        take_until("**"),
-        tag("**"),
-    ))(i)?;
+        previous = Some(c);
+    }
+
+    cells.push(&line[cell_start..]);
 
-    Ok((rest, Span::Bold(bolded_text.to_string())))
+    cells
 }
 
-// TODO: does not handle escaped sequences
-fn parse_strikethrough(i: &str) -> IResult<&str, Span> {
-    let (rest, (_, strikethrough, _)) = tuple((
-        //
-        tag("~~"),
-        take_until("~~"),
-        tag("~~"),
-    ))(i)?;
+fn parse_table_cell(cell: &str) -> Result<Vec<Span>, NomErr<'_>> {
+    let cell = cell.trim();
 
-    Ok((rest, Span::Strikethrough(strikethrough.to_string())))
+    if cell.is_empty() {
+        Ok(Vec::new())
+    } else {
+        parse_string(cell)
+    }
 }
 
-// TODO: does not handle escaped sequences
-fn parse_italics(i: &str) -> IResult<&str, Span> {
-    let (rest, (_, italics, _)) = tuple((
-        //
-        tag("*"),
-        take_until("*"),
-        tag("*"),
-    ))(i)?;
+/// `[label]: url` defines the url of every `[text][label]` link in the document
+fn parse_as_link_definition(i: &str) -> IResult<&str, Block> {
+    let (rest, _whitespace) = take_till(|c| c != '\n')(i)?;
+
+    let (rest, label) = link_text(rest)?;
+    let (rest, _) = tuple((tag(":"), space0))(rest)?;
+    let (rest, url) = link_url(rest)?;
+    let (rest, _) = tuple((space0, opt(link_title), space0))(rest)?;
+    let (rest, _) = peek(alt((tag("\n"), eof)))(rest)?;
 
-    Ok((rest, Span::Italics(italics.to_string())))
+    if label.trim().is_empty() || url.is_empty() {
+        return Err(nom::Err::Error(ParseError::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+
+    Ok((
+        rest,
+        Block::LinkDefinition {
+            label: label.to_string(),
+            url,
+        },
+    ))
 }
 
-// TODO: does not handle escaped sequences
-fn parse_equation(i: &'_ str) -> IResult<&'_ str, Span> {
-    let (rest, (_, italics, _)) = tuple((
-        //
-        tag("$$"),
-        take_until("$$"),
-        tag("$$"),
-    ))(i)?;
+/// speaker notes, either everything after a `???` line or a `<!-- notes: ... -->` comment
+fn parse_as_notes(i: &str) -> IResult<&str, Block> {
+    let (start, _whitespace) = take_till(|c| c != '\n')(i)?;
+
+    let separator: IResult<&str, _> = tuple((tag("???"), space0, alt((tag("\n"), eof))))(start);
+
+    if let Ok((body, _)) = separator {
+        let (rest, notes) = parse_block(body)?;
+        reject_code_in_notes(body, &notes)?;
+        return Ok((rest, Block::Notes(notes)));
+    }
+
+    let (body, _) = tuple((tag("<!--"), space0, tag("notes:")))(start)?;
+    let (rest, body) = take_until("-->")(body)
+        .map_err(|_: NomErr| ParseError::failure(start, ParseErrorKind::UnterminatedNotes))?;
+    let (rest, _) = tag("-->")(rest)?;
 
-    Ok((rest, Span::Equation(italics.to_string())))
+    let (_, notes) = parse_block(body.trim_end())?;
+    reject_code_in_notes(body, &notes)?;
+
+    Ok((rest, Block::Notes(notes)))
 }
 
-fn parse_regular_text(i: &'_ str) -> IResult<&'_ str, Span> {
-    if i.is_empty() {
-        return Err(nom::Err::Error(nom::error::Error::new(
-            "",
-            nom::error::ErrorKind::Eof,
-        )));
+/// notes are the argument of `\note{...}`, where the verbatim text of a code block can not be
+/// read by latex
+fn reject_code_in_notes<'a>(body: &'a str, notes: &[Block]) -> Result<(), NomErr<'a>> {
+    fn contains_code(blocks: &[Block]) -> bool {
+        blocks.iter().any(|block| match block {
+            Block::Code(_) => true,
+            Block::BeamerBlock(group) => contains_code(&group.contents),
+            Block::Quote(blocks) => contains_code(blocks),
+            _ => false,
+        })
     }
 
-    let (rest, text) =
-        take_until_parser_success(i, alt((parse_italics, parse_bold, parse_strikethrough)))?;
+    if !contains_code(notes) {
+        return Ok(());
+    }
 
-    Ok((rest, Span::Text(text.to_string())))
+    let fence = body.find("```").map_or(body, |idx| &body[idx..]);
+    Err(ParseError::failure(fence, ParseErrorKind::CodeInNotes))
 }
 
-fn take_until_parser_success<'a, T, ParserOutput>(
-    i: &'a str,
-    mut parser: T,
-) -> IResult<&'a str, &'a str>
-where
-    T: nom::Parser<&'a str, ParserOutput, nom::error::Error<&'a str>>,
-    T: FnMut(&'a str) -> IResult<&'a str, ParserOutput>,
-{
-    let mut current_slice = i;
-    let mut idx = 0;
+/// `###` and `####` headings inside of a slide
+fn parse_as_heading(i: &str) -> IResult<&str, Block> {
+    let (rest, _whitespace) = take_till(|c| c != '\n')(i)?;
 
-    loop {
-        if parser(current_slice).is_ok() {
-            break;
-        }
+    let (rest, level) = alt((tag("#### "), tag("### ")))(rest)?;
+    let (rest, text) = take_till(|c| c == '\n')(rest)?;
 
-        idx += 1;
+    Ok((
+        rest,
+        Block::Heading {
+            level: level.trim_end().len(),
+            text: parse_string(text.trim_end())?,
+        },
+    ))
+}
 
-        if let Some(slice) = i.get(idx..) {
-            current_slice = slice
+/// `>` block quotes, which become callouts when their first line is a marker like `[!NOTE]`
+fn parse_as_quote(i: &str) -> IResult<&str, Block> {
+    let (start, _whitespace) = take_till(|c| c != '\n')(i)?;
+    peek(tag(">"))(start)?;
+
+    // the quote without its `>` markers, and for each of its lines the offset in `inner` and in
+    // `start`, so that errors can be traced back to the markdown
+    let mut inner = String::new();
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    for line in start.split_inclusive('\n') {
+        // like in commonmark, the `>` can be indented by up to 3 spaces
+        let indentation = line.len() - line.trim_start_matches(' ').len();
+        let marker = if indentation <= 3 {
+            &line[indentation..]
         } else {
-            if idx >= i.len() {
-                // TODO: this might need to be an error - im unsure as of now
-                idx -= 1;
-                break;
+            line
+        };
+
+        let content = match marker.strip_prefix('>') {
+            Some(content) => content.strip_prefix(' ').unwrap_or(content),
+            None => break,
+        };
+
+        lines.push((inner.len(), offset + line.len() - content.len()));
+        inner.push_str(content);
+        offset += line.len();
+    }
+
+    let rest = &start[offset..];
+
+    let first_line_end = inner.find('\n').unwrap_or(inner.len());
+    let callout = callout_marker(&inner[..first_line_end]);
+
+    let body = match callout {
+        Some(_) => &inner[first_line_end..],
+        None => &inner[..],
+    }
+    .trim_end();
+
+    let contents = parse_block(body)
+        .map(|(_, contents)| contents)
+        .map_err(|e| remap_quote_error(e, &inner, &lines, start))?;
+
+    let block = match callout {
+        Some((kind, default_title, title)) => {
+            let title = if title.is_empty() {
+                vec![Span::Text(default_title.to_string())]
             } else {
-                continue;
-            }
+                parse_string(title).map_err(|e| remap_quote_error(e, &inner, &lines, start))?
+            };
+
+            Block::BeamerBlock(BeamerBlock {
+                kind,
+                title,
+                contents,
+            })
         }
-    }
+        None => Block::Quote(contents),
+    };
 
-    Ok((current_slice, i.get(0..idx).unwrap()))
+    Ok((rest, block))
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// move an error from the text of a quote without its `>` markers back into the markdown
+fn remap_quote_error<'a>(
+    error: NomErr<'_>,
+    inner: &str,
+    lines: &[(usize, usize)],
+    start: &'a str,
+) -> NomErr<'a> {
+    let remap = |input: &str| {
+        let inner_offset = (input.as_ptr() as usize)
+            .checked_sub(inner.as_ptr() as usize)
+            .filter(|offset| *offset <= inner.len())
+            .unwrap_or(inner.len());
+
+        let (inner_start, start_offset) = lines
+            .iter()
+            .take_while(|(inner_start, _)| *inner_start <= inner_offset)
+            .last()
+            .copied()
+            .unwrap_or((0, 0));
+
+        start
+            .get(start_offset + inner_offset - inner_start..)
+            .unwrap_or(start)
+    };
+
+    match error {
+        nom::Err::Error(e) => nom::Err::Error(ParseError {
+            input: remap(e.input),
+            kind: e.kind,
+        }),
+        nom::Err::Failure(e) => nom::Err::Failure(ParseError {
+            input: remap(e.input),
+            kind: e.kind,
+        }),
+        nom::Err::Incomplete(_) => unreachable!("only complete parsers are used"),
+    }
+}
+
+/// `[!WARNING]` or `[!WARNING] Custom title` on the first line of a quote: the kind of block,
+/// its default title and the custom title
+fn callout_marker(line: &str) -> Option<(BlockKind, &'static str, &str)> {
+    let (marker, title) = line.trim().strip_prefix("[!")?.split_once(']')?;
+
+    let (kind, default_title) = match marker.to_ascii_uppercase().as_str() {
+        "NOTE" => (BlockKind::Block, "Note"),
+        "TIP" => (BlockKind::Block, "Tip"),
+        "IMPORTANT" => (BlockKind::Block, "Important"),
+        "WARNING" => (BlockKind::Alert, "Warning"),
+        "CAUTION" => (BlockKind::Alert, "Caution"),
+        "EXAMPLE" => (BlockKind::Example, "Example"),
+        _ => return None,
+    };
+
+    Some((kind, default_title, title.trim()))
+}
+
+fn parse_as_directive(i: &str) -> IResult<&str, Block> {
+    let (rest, _whitespace) = take_till(|c| c != '\n')(i)?;
+
+    let (rest, directive) = alt((
+        map(tag("%NEWSLIDE"), |_| Directive::NewSlide),
+        map(tag("%PAUSE"), |_| Directive::Pause),
+        map(tag("%INCREMENTAL"), |_| Directive::Incremental),
+        picture_columns,
+        start_columns,
+        map(tag("%COLUMN"), |_| Directive::Column),
+        map(tag("%ENDCOLUMNS"), |_| Directive::EndColumns),
+    ))(rest)?;
+
+    Ok((rest, Block::Directive(directive)))
+}
+
+fn picture_columns(i: &str) -> IResult<&str, Directive> {
+    let (rest, _) = tag("%PICTURECOLUMNS=")(i)?;
+    let (rest, number) = take_till(|c| c == '\n')(rest)?;
+
+    match number.trim().parse::<usize>() {
+        Ok(columns) if columns > 0 => Ok((rest, Directive::PictureColumns(columns))),
+        _ => Err(ParseError::failure(
+            number,
+            ParseErrorKind::InvalidPictureColumns,
+        )),
+    }
+}
+
+/// `%COLUMNS` with optional comma separated options after a `=`: `TOP` or `CENTER` for the vertical
+/// alignment and a width ratio for each column, like `%COLUMNS=CENTER,2,1`
+fn start_columns(i: &str) -> IResult<&str, Directive> {
+    let (rest, _) = tag("%COLUMNS")(i)?;
+
+    let mut alignment = ColumnAlignment::Top;
+    let mut ratios = Vec::new();
+
+    let rest = match tag::<_, _, ParseError<&str>>("=")(rest) {
+        Ok((rest, _)) => {
+            let (rest, options) = take_till(|c| c == '\n')(rest)?;
+
+            for option in options.split(',') {
+                match option.trim() {
+                    "TOP" => alignment = ColumnAlignment::Top,
+                    "CENTER" => alignment = ColumnAlignment::Center,
+                    ratio => match ratio.parse::<f64>() {
+                        Ok(ratio) if ratio > 0.0 && ratio.is_finite() => ratios.push(ratio),
+                        _ => {
+                            return Err(ParseError::failure(
+                                ratio,
+                                ParseErrorKind::InvalidColumnOption(ratio.to_string()),
+                            ))
+                        }
+                    },
+                }
+            }
+
+            // ratios below 1 are fractions of the slide, which can not add up to more than all of it
+            if ratios.iter().all(|ratio| *ratio < 1.0) && ratios.iter().sum::<f64>() > 1.0 {
+                return Err(ParseError::failure(
+                    options,
+                    ParseErrorKind::InvalidColumnRatios(options.trim().to_string()),
+                ));
+            }
+
+            rest
+        }
+        Err(_) => rest,
+    };
+
+    Ok((rest, Directive::Columns { alignment, ratios }))
+}
+
+#[derive(Debug)]
+struct CodeHeader {
+    language: String,
+}
+
+fn code_block_header(i: &str) -> IResult<&str, CodeHeader> {
+    let (after_ticks, _ticks) = tag("```")(i)?;
+    let (rest, language_name) = take_till(|c| c == '\n')(after_ticks)?;
+    let (code_start, _newline) = tag("\n")(rest)?;
+
+    Ok((
+        code_start,
+        CodeHeader {
+            language: language_name.to_string(),
+        },
+    ))
+}
+
+fn collect_bullet_items(
+    flat: &mut Vec<(usize, ListKind, BulletItem)>,
+    current_indentation: usize,
+) -> BulletList {
+    // the first item at this level of indentation decides if the list is numbered or not
+    let kind = flat
+        .first()
+        .map(|(_, kind, _)| kind.clone())
+        .unwrap_or(ListKind::Unordered);
+
+    let mut items = Vec::new();
+
+    while !flat.is_empty() {
+        let (indentation, _kind, _bullet_data) = &flat[0];
+        // copy the data for borrowing rules
+        let indentation = *indentation;
+
+        match indentation.cmp(&current_indentation) {
+            Ordering::Greater => {
+                let nested = collect_bullet_items(flat, indentation);
+                items.push(BulletItem::Nested(nested));
+            }
+            Ordering::Less => {
+                // return back to the previous level of indentation
+                break;
+            }
+            // a different kind of marker starts a new list next to this one
+            Ordering::Equal if !kind.same_kind(&flat[0].1) => break,
+            Ordering::Equal => {
+                // we have the same level of indentation
+                let item = flat.remove(0);
+                items.push(item.2)
+            }
+        }
+    }
+
+    BulletList {
+        kind,
+        items,
+        incremental: false,
+    }
+}
+
+fn parse_string<'a>(i: &'a str) -> Result<Vec<Span>, NomErr<'a>> {
+    if i.is_empty() {
+        return Err(nom::Err::Error(ParseError::new(
+            i,
+            nom::error::ErrorKind::Eof,
+        )));
+    }
+
+    let mut spans = Vec::new();
+    let mut rest = i;
+
+    while !rest.is_empty() {
+        let previous = i[..i.len() - rest.len()].chars().next_back();
+
+        let (new_rest, span) = match parse_formatted_span(previous, rest) {
+            Ok(out) => out,
+            Err(nom::Err::Error(_)) => parse_regular_text(rest)?,
+            Err(e) => return Err(e),
+        };
+
+        rest = new_rest;
+        spans.push(span);
+    }
+
+    Ok(spans)
+}
+
+/// parse any span other than plain text from the start of `i`. `previous` is the character
+/// directly before `i`, which decides if an emphasis delimiter is allowed to open here
+fn parse_formatted_span(previous: Option<char>, i: &str) -> IResult<&str, Span> {
+    if let Some(delimiter) = i.chars().next().filter(|c| is_emphasis_delimiter(*c)) {
+        let next = i.trim_start_matches(delimiter).chars().next();
+
+        if !can_open(delimiter, previous, next) {
+            return Err(nom::Err::Error(ParseError::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            )));
+        }
+    }
+
+    alt((
+        parse_code,
+        parse_strikethrough,
+        parse_bold,
+        parse_italics,
+        parse_equation,
+        parse_link,
+        parse_autolink,
+    ))(i)
+}
+
+/// `[text](url)`, `[text][label]`, `[text][]` or `[text]`
+fn parse_link(i: &str) -> IResult<&str, Span> {
+    let (after_text, text) = link_text(i)?;
+
+    if let Ok((rest, url)) = link_destination(after_text) {
+        // a link without any text shows the url itself
+        if text.trim().is_empty() {
+            return Ok((rest, Span::Url(url)));
+        }
+
+        let text = parse_string(text)?;
+        return Ok((rest, Span::Link { text, url }));
+    }
+
+    if text.trim().is_empty() {
+        return Err(nom::Err::Error(ParseError::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+
+    let label: IResult<&str, _> = tuple((tag("["), take_till(|c| c == ']'), tag("]")))(after_text);
+
+    let (rest, label, kind) = match label {
+        Ok((rest, (_, label, _))) if !label.trim().is_empty() => (rest, label, ReferenceKind::Full),
+        // `[text][]` uses the text as the label, just like `[text]`
+        Ok((rest, _)) => (rest, text, ReferenceKind::Collapsed),
+        Err(_) => (after_text, text, ReferenceKind::Shortcut),
+    };
+
+    Ok((
+        rest,
+        Span::ReferenceLink {
+            text: parse_string(text)?,
+            label: label.to_string(),
+            kind,
+        },
+    ))
+}
+
+/// the text between the brackets of a link, which may contain nested brackets
+fn link_text(i: &str) -> IResult<&str, &str> {
+    let (content, _) = tag("[")(i)?;
+
+    let mut depth = 0;
+    let mut idx = 0;
+
+    while let Some(c) = content[idx..].chars().next() {
+        match c {
+            '\\' => {
+                idx += 1 + content[idx + 1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            '`' => {
+                if let Ok((rest, _)) = parse_code(&content[idx..]) {
+                    idx = content.len() - rest.len();
+                    continue;
+                }
+            }
+            '[' => depth += 1,
+            ']' if depth == 0 => return Ok((&content[idx + 1..], &content[..idx])),
+            ']' => depth -= 1,
+            _ => (),
+        }
+
+        idx += c.len_utf8();
+    }
+
+    Err(nom::Err::Error(ParseError::new(
+        i,
+        nom::error::ErrorKind::TakeUntil,
+    )))
+}
+
+/// `(url)`, `(<url>)` or `(url "title")` directly after the text of a link. The title is not
+/// shown anywhere in a pdf so it is thrown away
+fn link_destination(i: &str) -> IResult<&str, String> {
+    let (rest, _) = tuple((tag("("), space0))(i)?;
+    let (rest, url) = link_url(rest)?;
+    let (rest, _) = tuple((space0, opt(link_title), space0, tag(")")))(rest)?;
+
+    Ok((rest, url))
+}
+
+fn link_url(i: &str) -> IResult<&str, String> {
+    let bracketed: IResult<&str, _> =
+        tuple((tag("<"), take_till(|c| c == '>' || c == '\n'), tag(">")))(i);
+
+    if let Ok((rest, (_, url, _))) = bracketed {
+        return Ok((rest, url.to_string()));
+    }
+
+    // parenthesis in the url are allowed as long as they are balanced
+    let mut depth = 0;
+    let mut end = i.len();
+
+    for (idx, c) in i.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                end = idx;
+                break;
+            }
+            ')' => depth -= 1,
+            c if c.is_whitespace() => {
+                end = idx;
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    Ok((&i[end..], i[..end].to_string()))
+}
+
+fn link_title(i: &str) -> IResult<&str, &str> {
+    alt((
+        |i| -> IResult<&str, &str> {
+            let (rest, (_, title, _)) = tuple((tag("\""), take_till(|c| c == '"'), tag("\"")))(i)?;
+            Ok((rest, title))
+        },
+        |i| -> IResult<&str, &str> {
+            let (rest, (_, title, _)) = tuple((tag("'"), take_till(|c| c == '\''), tag("'")))(i)?;
+            Ok((rest, title))
+        },
+    ))(i)
+}
+
+/// `<https://example.com>` or `<someone@example.com>`
+fn parse_autolink(i: &str) -> IResult<&str, Span> {
+    let (rest, (_, url, _)) = tuple((
+        tag("<"),
+        take_till(|c: char| c == '>' || c == '<' || c.is_whitespace()),
+        tag(">"),
+    ))(i)?;
+
+    let scheme = url.split(':').next().unwrap_or("");
+
+    let has_scheme = url.contains(':')
+        && (2..=32).contains(&scheme.len())
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'));
+
+    if has_scheme {
+        return Ok((rest, Span::Url(url.to_string())));
+    }
+
+    let is_email = url
+        .split_once('@')
+        .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+
+    if is_email {
+        return Ok((
+            rest,
+            Span::Link {
+                text: vec![Span::Text(url.to_string())],
+                url: format!("mailto:{url}"),
+            },
+        ));
+    }
+
+    Err(nom::Err::Error(ParseError::new(
+        i,
+        nom::error::ErrorKind::Verify,
+    )))
+}
+
+/// labels of reference links are matched without case and with any whitespace collapsed
+pub(crate) fn normalize_link_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn parse_code(i: &str) -> IResult<&str, Span> {
+    let (content_start, ticks) = take_while1(|c| c == '`')(i)?;
+
+    // the span is closed by the next run of exactly as many backticks as it was opened with
+    let mut search_from = 0;
+
+    let (code, rest) = loop {
+        let run_start = match content_start[search_from..].find('`') {
+            Some(offset) => search_from + offset,
+            None => {
+                return Err(nom::Err::Error(ParseError::new(
+                    i,
+                    nom::error::ErrorKind::TakeUntil,
+                )))
+            }
+        };
+
+        let run_length = content_start[run_start..]
+            .chars()
+            .take_while(|c| *c == '`')
+            .count();
+
+        if run_length == ticks.len() {
+            break (
+                &content_start[..run_start],
+                &content_start[run_start + run_length..],
+            );
+        }
+
+        search_from = run_start + run_length;
+    };
+
+    let code = code.replace('\n', " ");
+
+    // a single space on both sides is stripped so that code starting with a backtick can be written
+    let code = if !code.trim().is_empty() && code.starts_with(' ') && code.ends_with(' ') {
+        code[1..code.len() - 1].to_string()
+    } else {
+        code
+    };
+
+    // a code span followed by `{=latex}` is raw latex that should not be escaped
+    if let Ok((rest, _)) = tag::<_, _, ParseError<&str>>(RAW_LATEX_ATTRIBUTE)(rest) {
+        return Ok((rest, Span::Latex(code)));
+    }
+
+    Ok((rest, Span::Code(code)))
+}
+
+fn parse_bold(i: &str) -> IResult<&str, Span> {
+    let (rest, bolded_text) = alt((|i| parse_delimited(i, "**"), |i| parse_delimited(i, "__")))(i)?;
+
+    Ok((rest, Span::Bold(parse_string(bolded_text)?)))
+}
+
+fn parse_strikethrough(i: &str) -> IResult<&str, Span> {
+    let (rest, strikethrough) = parse_delimited(i, "~~")?;
+
+    Ok((rest, Span::Strikethrough(parse_string(strikethrough)?)))
+}
+
+fn parse_italics(i: &str) -> IResult<&str, Span> {
+    let (rest, italics) = alt((
+        //
+        |i| parse_delimited(i, "*"),
+        |i| parse_delimited(i, "_"),
+    ))(i)?;
+
+    Ok((rest, Span::Italics(parse_string(italics)?)))
+}
+
+fn parse_equation(i: &'_ str) -> IResult<&'_ str, Span> {
+    alt((parse_display_equation, parse_inline_equation))(i)
+}
+
+/// `$$...$$` or `\[...\]` in the middle of some text
+fn parse_display_equation(i: &'_ str) -> IResult<&'_ str, Span> {
+    let (rest, (_, equation, _)) = alt((
+        tuple((tag("$$"), take_until("$$"), tag("$$"))),
+        tuple((tag("\\["), take_until("\\]"), tag("\\]"))),
+    ))(i)?;
+
+    Ok((rest, Span::Equation(equation.to_string())))
+}
+
+/// `$...$` or `\(...\)`
+fn parse_inline_equation(i: &'_ str) -> IResult<&'_ str, Span> {
+    let parenthesis: IResult<&str, _> = tuple((tag("\\("), take_until("\\)"), tag("\\)")))(i);
+
+    if let Ok((rest, (_, math, _))) = parenthesis {
+        return Ok((rest, Span::Math(math.to_string())));
+    }
+
+    let error = || nom::Err::Error(ParseError::new(i, nom::error::ErrorKind::TakeUntil));
+
+    let (content, _) = tag("$")(i)?;
+
+    // `$ 5` and `$$` do not start any math
+    match content.chars().next() {
+        Some(c) if !c.is_whitespace() && c != '$' => (),
+        _ => return Err(error()),
+    }
+
+    let mut previous = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            // `\$` is a dollar sign inside of the math
+            '\\' => {
+                chars.next();
+            }
+            // the closing dollar cannot follow whitespace and cannot be followed by a digit,
+            // which keeps `$5 and $10` as text
+            '$' if !previous.is_some_and(char::is_whitespace)
+                && !chars.peek().is_some_and(|(_, next)| next.is_ascii_digit()) =>
+            {
+                return Ok((&content[idx + 1..], Span::Math(content[..idx].to_string())));
+            }
+            _ => (),
+        }
+
+        previous = Some(c);
+    }
+
+    Err(error())
+}
+
+fn parse_regular_text(i: &'_ str) -> IResult<&'_ str, Span> {
+    if i.is_empty() {
+        return Err(nom::Err::Error(ParseError::new(
+            "",
+            nom::error::ErrorKind::Eof,
+        )));
+    }
+
+    let mut text = String::new();
+    let mut previous = None;
+    let mut chars = i.char_indices().peekable();
+    let mut rest = "";
+
+    while let Some((idx, c)) = chars.next() {
+        // the first character is always text, otherwise we would not have been called.
+        // only a few characters can start a span, so the rest is never parsed again.
+        // failures are left for `parse_string` to report
+        if idx != 0
+            && could_start_span(c)
+            && !matches!(
+                parse_formatted_span(previous, &i[idx..]),
+                Err(nom::Err::Error(_))
+            )
+        {
+            rest = &i[idx..];
+            break;
+        }
+
+        // a backslash before punctuation means the punctuation is meant literally
+        if let Some((_, escaped)) = chars.peek().filter(|_| c == '\\') {
+            if escaped.is_ascii_punctuation() {
+                text.push(*escaped);
+                previous = Some(*escaped);
+                chars.next();
+                continue;
+            }
+        }
+
+        text.push(c);
+        previous = Some(c);
+    }
+
+    Ok((rest, Span::Text(text)))
+}
+
+/// the first characters of code, emphasis, equations, links and autolinks
+fn could_start_span(c: char) -> bool {
+    matches!(c, '`' | '*' | '_' | '~' | '$' | '\\' | '[' | '<')
+}
+
+/// parse a span surrounded by `delimiter` (`**`, `_`, `~~`, ...), returning the text between
+/// the opening and closing delimiters
+fn parse_delimited<'a>(i: &'a str, delimiter: &'static str) -> IResult<&'a str, &'a str> {
+    let error = || nom::Err::Error(ParseError::new(i, nom::error::ErrorKind::TakeUntil));
+
+    let (content, _) = tag(delimiter)(i)?;
+
+    // all delimiters are a repeated ascii character
+    let delimiter_char = delimiter.chars().next().unwrap();
+
+    // nothing comes before `i` as far as we know, that is checked by `parse_formatted_span`
+    let next = i.trim_start_matches(delimiter_char).chars().next();
+
+    if !can_open(delimiter_char, None, next) {
+        return Err(error());
+    }
+
+    // an unclosed delimiter is shown as text, like in commonmark
+    let (inner, rest) = match find_closing_delimiter(content, delimiter_char, delimiter.len()) {
+        Some((inner, rest)) if !inner.is_empty() => (inner, rest),
+        _ => return Err(error()),
+    };
+
+    Ok((rest, inner))
+}
+
+/// find the run of `delimiter` characters that closes a span of `length` delimiters, starting
+/// the search from the content of the span. Runs that open and close spans nested inside the
+/// content are skipped over, as are code spans and escaped characters.
+///
+/// Returns the content of the span and the input after the closing delimiter
+fn find_closing_delimiter(content: &str, delimiter: char, length: usize) -> Option<(&str, &str)> {
+    // lengths of the delimiter runs in the content that have not been closed yet
+    let mut unclosed_runs: Vec<usize> = Vec::new();
+    let mut idx = 0;
+
+    while let Some(c) = content[idx..].chars().next() {
+        if c == '\\' {
+            // skip the backslash and whatever character it escapes
+            idx += 1 + content[idx + 1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+
+        if c == '`' {
+            idx = match parse_code(&content[idx..]) {
+                Ok((rest, _)) => content.len() - rest.len(),
+                Err(_) => idx + content[idx..].len() - content[idx..].trim_start_matches('`').len(),
+            };
+            continue;
+        }
+
+        if c != delimiter {
+            idx += c.len_utf8();
+            continue;
+        }
+
+        let run_end = content.len() - content[idx..].trim_start_matches(delimiter).len();
+        let previous = content[..idx].chars().next_back();
+        let next = content[run_end..].chars().next();
+
+        let mut remaining = run_end - idx;
+
+        if can_close(delimiter, previous, next) {
+            // close any runs that were opened inside of the span first
+            while remaining > 0 {
+                match unclosed_runs.last_mut() {
+                    Some(unclosed) => {
+                        let used = remaining.min(*unclosed);
+                        *unclosed -= used;
+                        remaining -= used;
+
+                        if *unclosed == 0 {
+                            unclosed_runs.pop();
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            if remaining >= length {
+                return Some((&content[..run_end - length], &content[run_end..]));
+            }
+        }
+
+        if remaining > 0 && can_open(delimiter, previous, next) {
+            unclosed_runs.push(remaining);
+        }
+
+        idx = run_end;
+    }
+
+    None
+}
+
+fn is_emphasis_delimiter(c: char) -> bool {
+    matches!(c, '*' | '_' | '~')
+}
+
+/// a run of delimiters that can start a span: `*text`, but not `2 * 3`.
+/// `None` means the start or end of the text, which behaves like whitespace
+fn is_left_flanking(previous: Option<char>, next: Option<char>) -> bool {
+    let is_whitespace = |c: Option<char>| c.is_none_or(char::is_whitespace);
+    let is_punctuation = |c: Option<char>| c.is_some_and(|c| c.is_ascii_punctuation());
+
+    !is_whitespace(next)
+        && (!is_punctuation(next) || is_whitespace(previous) || is_punctuation(previous))
+}
+
+/// a run of delimiters that can end a span: `text*`, but not `2 * 3`
+fn is_right_flanking(previous: Option<char>, next: Option<char>) -> bool {
+    is_left_flanking(next, previous)
+}
+
+fn can_open(delimiter: char, previous: Option<char>, next: Option<char>) -> bool {
+    let left = is_left_flanking(previous, next);
+
+    // underscores inside of words (snake_case_names) are not emphasis
+    if delimiter == '_' {
+        left && (!is_right_flanking(previous, next)
+            || previous.is_some_and(|c| c.is_ascii_punctuation()))
+    } else {
+        left
+    }
+}
+
+fn can_close(delimiter: char, previous: Option<char>, next: Option<char>) -> bool {
+    let right = is_right_flanking(previous, next);
+
+    if delimiter == '_' {
+        right
+            && (!is_left_flanking(previous, next) || next.is_some_and(|c| c.is_ascii_punctuation()))
+    } else {
+        right
+    }
+}
+
+fn take_until_parser_success<'a, T, ParserOutput>(
+    i: &'a str,
+    mut parser: T,
+) -> IResult<&'a str, &'a str>
+where
+    T: nom::Parser<&'a str, ParserOutput, ParseError<&'a str>>,
+    T: FnMut(&'a str) -> IResult<&'a str, ParserOutput>,
+{
+    let mut current_slice = i;
+    let mut idx = 0;
+
+    loop {
+        if parser(current_slice).is_ok() {
+            break;
+        }
+
+        idx += 1;
+
+        if let Some(slice) = i.get(idx..) {
+            current_slice = slice
+        } else {
+            if idx >= i.len() {
+                // TODO: this might need to be an error - im unsure as of now
+                idx -= 1;
+                break;
+            } else {
+                continue;
+            }
+        }
+    }
+
+    Ok((current_slice, i.get(0..idx).unwrap()))
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Block {
     Paragraph(Vec<Span>),
-    BulletedList(Vec<BulletItem>),
+    BulletedList(BulletList),
     Picture(ParsePicture),
     Code(Code),
+    Table(Table),
+    // raw latex from a ```{=latex} fenced block
+    Latex(String),
+    // display math on its own lines
+    Equation(String),
     Directive(Directive),
+    // `###` or `####` inside of a slide
+    Heading { level: usize, text: Vec<Span> },
+    BeamerBlock(BeamerBlock),
+    // built from `%COLUMNS` directives after parsing
+    Columns(Columns),
+    // `>` block quote
+    Quote(Vec<Block>),
+    // speaker notes, moved to the slide once it has been parsed
+    Notes(Vec<Block>),
+    // `[label]: url`, removed from the slides once every reference link has been resolved
+    LinkDefinition { label: String, url: String },
+}
+
+/// a titled beamer `block`, `alertblock` or `exampleblock` environment
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct BeamerBlock {
+    pub(crate) kind: BlockKind,
+    pub(crate) title: Vec<Span>,
+    pub(crate) contents: Vec<Block>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum BlockKind {
+    Block,
+    Alert,
+    Example,
+}
+
+impl BlockKind {
+    pub(crate) fn environment(&self) -> &'static str {
+        match self {
+            BlockKind::Block => "block",
+            BlockKind::Alert => "alertblock",
+            BlockKind::Example => "exampleblock",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -426,14 +1763,83 @@ pub(crate) struct ParsePicture {
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum PictureDirective {
+    // the picture goes below the text
     Vertical,
     Width(String),
     Height(String),
+    // the side of the text the picture is placed on
+    Left,
+    Right,
+    Top,
+    // fractions of the width of the slide for the text and the picture next to it
+    Split { text: f64, picture: f64 },
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Directive {
+    // copy the slide so far into a new slide
     NewSlide,
+    // reveal the blocks after it on the next overlay of the same frame
+    Pause,
+    // reveal the items of lists one at a time
+    Incremental,
+    // how many pictures are placed next to each other
+    PictureColumns(usize),
+    // the blocks up to `%ENDCOLUMNS` are placed in columns with the given width ratios
+    Columns {
+        alignment: ColumnAlignment,
+        ratios: Vec<f64>,
+    },
+    // start the next column
+    Column,
+    EndColumns,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ColumnAlignment {
+    Top,
+    Center,
+}
+
+/// beamer `columns` built from the blocks between `%COLUMNS` and `%ENDCOLUMNS`
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Columns {
+    pub(crate) alignment: ColumnAlignment,
+    pub(crate) columns: Vec<Column>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Column {
+    // fraction of the width of the text
+    pub(crate) width: f64,
+    pub(crate) contents: Vec<Block>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct BulletList {
+    pub(crate) kind: ListKind,
+    pub(crate) items: Vec<BulletItem>,
+    // items are revealed one overlay at a time
+    pub(crate) incremental: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum ListKind {
+    // `*`, `-` or `+` items, rendered as an itemize
+    Unordered,
+    // `1.` or `1)` items, rendered as an enumerate that counts from `start`
+    Ordered { start: usize },
+}
+
+impl ListKind {
+    /// both numbered or both bulleted, whatever the start number
+    fn same_kind(&self, other: &ListKind) -> bool {
+        matches!(
+            (self, other),
+            (ListKind::Unordered, ListKind::Unordered)
+                | (ListKind::Ordered { .. }, ListKind::Ordered { .. })
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -441,22 +1847,61 @@ pub(crate) enum BulletItem {
     // a single bullet point that is at the same indentation level
     Single(Vec<Span>),
     // a set of bullet points at one more indentation level than the current level
-    Nested(Vec<BulletItem>),
+    Nested(BulletList),
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Span {
-    Bold(String),          //
-    Strikethrough(String), //
-    Italics(String),       //
-    Text(String),          //
+    Bold(Vec<Span>),          //
+    Strikethrough(Vec<Span>), //
+    Italics(Vec<Span>),       //
+    Text(String),             //
+    // display math: $$...$$ or \[...\]
     Equation(String),
+    // inline math: $...$ or \(...\)
+    Math(String),
+    Code(String),
+    // [text](url)
+    Link {
+        text: Vec<Span>,
+        url: String,
+    },
+    // [text][label], [text][] or [text]. These are replaced with links during postprocessing
+    // and are only written as text if the label was never defined
+    ReferenceLink {
+        text: Vec<Span>,
+        label: String,
+        kind: ReferenceKind,
+    },
+    // <https://example.com>
+    Url(String),
+    // raw latex from a `...`{=latex} span
+    Latex(String),
+}
+
+/// how a reference link was written, so that an undefined one is shown as it was typed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ReferenceKind {
+    // [text][label]
+    Full,
+    // [text][]
+    Collapsed,
+    // [text]
+    Shortcut,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn unordered(items: Vec<BulletItem>) -> BulletList {
+        BulletList {
+            kind: ListKind::Unordered,
+            items,
+            incremental: false,
+        }
+    }
+
     #[test]
     fn bold_1() {
         let text = "**some bold thing here** other stuff";
@@ -464,17 +1909,144 @@ mod tests {
 
         let out = out.unwrap();
 
-        assert_eq!(out.1, Span::Bold("some bold thing here".into()));
+        assert_eq!(
+            out.1,
+            Span::Bold(vec![Span::Text("some bold thing here".into())])
+        );
         assert_eq!(out.0, " other stuff");
     }
 
     #[test]
-    #[ignore]
     fn bold_escapted() {
         let text = "**some bold thing here \\* and continues**";
         let out = parse_bold(text);
         dbg!(&out);
-        out.unwrap();
+        let out = out.unwrap();
+
+        assert_eq!(
+            out.1,
+            Span::Bold(vec![Span::Text(
+                "some bold thing here * and continues".into()
+            )])
+        );
+        assert_eq!(out.0, "");
+    }
+
+    #[test]
+    fn nested_emphasis() {
+        let text = "**bold with *italic* inside** *it __bold__ more* ***both***";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Bold(vec![
+                Span::Text("bold with ".into()),
+                Span::Italics(vec![Span::Text("italic".into())]),
+                Span::Text(" inside".into()),
+            ]),
+            Span::Text(" ".into()),
+            Span::Italics(vec![
+                Span::Text("it ".into()),
+                Span::Bold(vec![Span::Text("bold".into())]),
+                Span::Text(" more".into()),
+            ]),
+            Span::Text(" ".into()),
+            Span::Bold(vec![Span::Italics(vec![Span::Text("both".into())])]),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn nested_emphasis_shared_closing_run() {
+        let text = "**bold *italic***";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![Span::Bold(vec![
+            Span::Text("bold ".into()),
+            Span::Italics(vec![Span::Text("italic".into())]),
+        ])];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn underscore_emphasis() {
+        let text = "__bold__ _italics_";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Bold(vec![Span::Text("bold".into())]),
+            Span::Text(" ".into()),
+            Span::Italics(vec![Span::Text("italics".into())]),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn intraword_underscores_are_text() {
+        let text = "call snake_case_name and __init__";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Text("call snake_case_name and ".into()),
+            Span::Bold(vec![Span::Text("init".into())]),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn spaced_asterisks_are_text() {
+        let text = "2 * 3 * 4 = 24";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        assert_eq!(out, vec![Span::Text("2 * 3 * 4 = 24".into())]);
+    }
+
+    #[test]
+    fn escaped_characters() {
+        let text = "\\*not italics\\* costs \\$5 \\~ \\` \\_";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        assert_eq!(out, vec![Span::Text("*not italics* costs $5 ~ ` _".into())]);
+    }
+
+    #[test]
+    fn unclosed_delimiters() {
+        // unclosed delimiters are text, like in commonmark
+        for text in [
+            "not *italics",
+            "2**10",
+            "**not bold *not italics",
+            "call __init please",
+            "**Note:",
+        ] {
+            let out = parse_string(text);
+            dbg!(&out);
+            let out = out.unwrap();
+
+            let joined: String = out
+                .iter()
+                .map(|span| match span {
+                    Span::Text(text) => text.as_str(),
+                    other => panic!("unexpected span {:?}", other),
+                })
+                .collect();
+            assert_eq!(joined, text);
+        }
     }
 
     #[test]
@@ -486,7 +2058,7 @@ mod tests {
 
         assert_eq!(
             out.1,
-            Span::Strikethrough("some strikethrough thing here".into())
+            Span::Strikethrough(vec![Span::Text("some strikethrough thing here".into())])
         );
 
         assert_eq!(out.0, " other stuff");
@@ -499,58 +2071,290 @@ mod tests {
 
         let out = out.unwrap();
 
-        assert_eq!(out.1, Span::Italics("some italic thing here".into()));
+        assert_eq!(
+            out.1,
+            Span::Italics(vec![Span::Text("some italic thing here".into())])
+        );
+
+        assert_eq!(out.0, " other stuff");
+    }
+
+    #[test]
+    fn text_1() {
+        let text = "start text **bold text**";
+        let out = parse_regular_text(text);
+        dbg!(&out);
+        let out = out.unwrap();
+        assert_eq!(out.1, Span::Text("start text ".to_string()));
+        assert_eq!(out.0, "**bold text**");
+    }
+
+    #[test]
+    fn text_2_only_text() {
+        let text = "only text";
+        let out = parse_regular_text(text);
+        dbg!(&out);
+        let out = out.unwrap();
+        assert_eq!(out.1, Span::Text("only text".to_string()));
+        assert_eq!(out.0, "");
+    }
+
+    #[test]
+    fn block_1() {
+        let text = "\n\nblock text here\n\nanother block of something here \n## a new header here";
+        let out = parse_block(text);
+        dbg!(&out);
+        let _out = out.unwrap();
+    }
+
+    #[test]
+    fn parse_string_1() {
+        let text = "something **bold** *italics* ~~strike~~";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Text("something ".into()),
+            Span::Bold(vec![Span::Text("bold".into())]),
+            Span::Text(" ".into()),
+            Span::Italics(vec![Span::Text("italics".into())]),
+            Span::Text(" ".into()),
+            Span::Strikethrough(vec![Span::Text("strike".into())]),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn code_1() {
+        let text = "`some_code()` other stuff";
+        let out = parse_code(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        assert_eq!(out.1, Span::Code("some_code()".into()));
+        assert_eq!(out.0, " other stuff");
+    }
+
+    #[test]
+    fn code_multiple_backticks() {
+        let text = "`` a `nested` **span** `` rest";
+        let out = parse_code(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        assert_eq!(out.1, Span::Code("a `nested` **span**".into()));
+        assert_eq!(out.0, " rest");
+
+        // no closing run of the same length
+        assert!(parse_code("``code`").is_err());
+    }
+
+    #[test]
+    fn raw_latex_span() {
+        let text = "`\\textcolor{red}{x}`{=latex} rest";
+        let out = parse_code(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        assert_eq!(out.1, Span::Latex("\\textcolor{red}{x}".into()));
+        assert_eq!(out.0, " rest");
+    }
+
+    #[test]
+    fn raw_latex_block() {
+        let code = "```{=latex}\n\\vspace{1cm}\n```";
+        let out = parse_as_code(code);
+        dbg!(&out);
+        let out = out.unwrap().1;
+
+        assert_eq!(out, Block::Latex("\\vspace{1cm}\n".into()));
+    }
+
+    #[test]
+    fn parse_string_with_code() {
+        let text = "call `*ptr` with **care**";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Text("call ".into()),
+            Span::Code("*ptr".into()),
+            Span::Text(" with ".into()),
+            Span::Bold(vec![Span::Text("care".into())]),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn inline_math() {
+        let text = "with $x_1 * y$ and \\(z^2\\) but $5 and $10 are money";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Text("with ".into()),
+            Span::Math("x_1 * y".into()),
+            Span::Text(" and ".into()),
+            Span::Math("z^2".into()),
+            Span::Text(" but $5 and $10 are money".into()),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn display_math_in_text() {
+        let text = "energy $$E = mc^2$$ and \\[F = ma\\]";
+        let out = parse_string(text);
+        dbg!(&out);
+        let out = out.unwrap();
+
+        let expected = vec![
+            Span::Text("energy ".into()),
+            Span::Equation("E = mc^2".into()),
+            Span::Text(" and ".into()),
+            Span::Equation("F = ma".into()),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn display_math_block() {
+        let text = "
+            ## Slide Name
+
+            $$
+            e^{i \\pi} - 1 = 0
+            $$
+
+            \\[
+            a &= b \\\\
+            c &= d
+            \\]
+
+            REST";
+
+        let slide = parse_slide(text);
+        dbg!(&slide);
+        let slide = slide.unwrap().1;
 
-        assert_eq!(out.0, " other stuff");
+        assert_eq!(
+            slide.contents[0],
+            Block::Equation("            e^{i \\pi} - 1 = 0".into())
+        );
+        assert!(matches!(slide.contents[1], Block::Equation(_)));
+        assert_eq!(
+            slide.contents[2],
+            Block::Paragraph(vec![Span::Text("REST".into())])
+        );
     }
 
     #[test]
-    fn text_1() {
-        let text = "start text **bold text**";
-        let out = parse_regular_text(text);
+    fn inline_links() {
+        let text =
+            "see [the **docs**](https://example.com/a_(b) \"title\") or **[bold link](<x y>)**";
+        let out = parse_string(text);
         dbg!(&out);
         let out = out.unwrap();
-        assert_eq!(out.1, Span::Text("start text ".to_string()));
-        assert_eq!(out.0, "**bold text**");
+
+        let expected = vec![
+            Span::Text("see ".into()),
+            Span::Link {
+                text: vec![
+                    Span::Text("the ".into()),
+                    Span::Bold(vec![Span::Text("docs".into())]),
+                ],
+                url: "https://example.com/a_(b)".into(),
+            },
+            Span::Text(" or ".into()),
+            Span::Bold(vec![Span::Link {
+                text: vec![Span::Text("bold link".into())],
+                url: "x y".into(),
+            }]),
+        ];
+
+        assert_eq!(out, expected);
     }
 
     #[test]
-    fn text_2_only_text() {
-        let text = "only text";
-        let out = parse_regular_text(text);
+    fn autolinks() {
+        let text = "<https://example.com/#top> <me@example.com> <not a link>";
+        let out = parse_string(text);
         dbg!(&out);
         let out = out.unwrap();
-        assert_eq!(out.1, Span::Text("only text".to_string()));
-        assert_eq!(out.0, "");
-    }
 
-    #[test]
-    fn block_1() {
-        let text = "\n\nblock text here\n\nanother block of something here \n## a new header here";
-        let out = parse_block(text);
-        dbg!(&out);
-        let _out = out.unwrap();
+        let expected = vec![
+            Span::Url("https://example.com/#top".into()),
+            Span::Text(" ".into()),
+            Span::Link {
+                text: vec![Span::Text("me@example.com".into())],
+                url: "mailto:me@example.com".into(),
+            },
+            Span::Text(" <not a link>".into()),
+        ];
+
+        assert_eq!(out, expected);
     }
 
     #[test]
-    fn parse_string_1() {
-        let text = "something **bold** *italics* ~~strike~~";
+    fn reference_links() {
+        let text = "[full][Some Ref] [collapsed][] [shortcut]";
         let out = parse_string(text);
         dbg!(&out);
         let out = out.unwrap();
 
+        let reference = |text: &str, label: &str, kind| Span::ReferenceLink {
+            text: vec![Span::Text(text.into())],
+            label: label.into(),
+            kind,
+        };
+
         let expected = vec![
-            Span::Text("something ".into()),
-            Span::Bold("bold".into()),
+            reference("full", "Some Ref", ReferenceKind::Full),
             Span::Text(" ".into()),
-            Span::Italics("italics".into()),
+            reference("collapsed", "collapsed", ReferenceKind::Collapsed),
             Span::Text(" ".into()),
-            Span::Strikethrough("strike".into()),
+            reference("shortcut", "shortcut", ReferenceKind::Shortcut),
         ];
 
         assert_eq!(out, expected);
     }
 
+    #[test]
+    fn link_definitions() {
+        let text = "
+            ## Slide Name
+
+            text
+
+            [some ref]: https://example.com \"title\"
+            [other]: <https://example.org>";
+
+        let slide = parse_slide(text);
+        dbg!(&slide);
+        let slide = slide.unwrap().1;
+
+        let expected = vec![
+            Block::Paragraph(vec![Span::Text("text".into())]),
+            Block::LinkDefinition {
+                label: "some ref".into(),
+                url: "https://example.com".into(),
+            },
+            Block::LinkDefinition {
+                label: "other".into(),
+                url: "https://example.org".into(),
+            },
+        ];
+
+        assert_eq!(slide.contents, expected);
+    }
+
     #[test]
     fn parse_single_bullet_1() {
         let text = "* bullet text";
@@ -565,11 +2369,13 @@ mod tests {
         let out = parse_block_as_bullets(text);
         dbg!(&out);
         let out = out.unwrap();
-        let expected = Block::BulletedList(vec![
+        let expected = Block::BulletedList(unordered(vec![
             BulletItem::Single(vec![Span::Text("bullet text".into())]),
             BulletItem::Single(vec![Span::Text("bullet text".into())]),
-            BulletItem::Single(vec![Span::Bold("bolded bullet text".into())]),
-        ]);
+            BulletItem::Single(vec![Span::Bold(vec![Span::Text(
+                "bolded bullet text".into(),
+            )])]),
+        ]));
         assert_eq!(out.1, expected);
     }
 
@@ -587,19 +2393,219 @@ mod tests {
         let out = parse_block_as_bullets(text);
         dbg!(&out);
         let out = out.unwrap();
-        let expected = Block::BulletedList(vec![
+        let expected = Block::BulletedList(unordered(vec![
             BulletItem::Single(vec![Span::Text("item".into())]),
             BulletItem::Single(vec![Span::Text("item".into())]),
-            BulletItem::Nested(vec![
+            BulletItem::Nested(unordered(vec![
                 BulletItem::Single(vec![Span::Text("nested item".into())]),
                 BulletItem::Single(vec![Span::Text("nested item".into())]),
-            ]),
+            ])),
             BulletItem::Single(vec![Span::Text("regular item".into())]),
-        ]);
+        ]));
+
+        assert_eq!(out.1, expected)
+    }
+
+    #[test]
+    fn parse_ordered_bullet_block() {
+        let text = "3. third\n4) fourth";
+        let out = parse_block_as_bullets(text);
+        dbg!(&out);
+        let out = out.unwrap();
+        let expected = Block::BulletedList(BulletList {
+            kind: ListKind::Ordered { start: 3 },
+            items: vec![
+                BulletItem::Single(vec![Span::Text("third".into())]),
+                BulletItem::Single(vec![Span::Text("fourth".into())]),
+            ],
+            incremental: false,
+        });
+        assert_eq!(out.1, expected);
+    }
+
+    #[test]
+    fn parse_mixed_nested_bullet_block() {
+        let text = r#"
+- item
+    1. ordered item
+        + unordered item
+    2. ordered item
++ item
+        "#;
+
+        let out = parse_block_as_bullets(text);
+        dbg!(&out);
+        let out = out.unwrap();
+        let expected = Block::BulletedList(unordered(vec![
+            BulletItem::Single(vec![Span::Text("item".into())]),
+            BulletItem::Nested(BulletList {
+                kind: ListKind::Ordered { start: 1 },
+                items: vec![
+                    BulletItem::Single(vec![Span::Text("ordered item".into())]),
+                    BulletItem::Nested(unordered(vec![BulletItem::Single(vec![Span::Text(
+                        "unordered item".into(),
+                    )])])),
+                    BulletItem::Single(vec![Span::Text("ordered item".into())]),
+                ],
+                incremental: false,
+            }),
+            BulletItem::Single(vec![Span::Text("item".into())]),
+        ]));
 
         assert_eq!(out.1, expected)
     }
 
+    #[test]
+    fn new_list_for_a_different_marker() {
+        let item = |text: &str| BulletItem::Single(vec![Span::Text(text.into())]);
+        let ordered = |items| BulletList {
+            kind: ListKind::Ordered { start: 1 },
+            items,
+            incremental: false,
+        };
+
+        let (_, blocks) = parse_block("- a\n- b\n1. one\n2. two\n").unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                Block::BulletedList(unordered(vec![item("a"), item("b")])),
+                Block::BulletedList(ordered(vec![item("one"), item("two")])),
+            ]
+        );
+
+        let (_, blocks) = parse_block("- a\n    - x\n    1. y\n- b\n").unwrap();
+        assert_eq!(
+            blocks,
+            vec![Block::BulletedList(unordered(vec![
+                item("a"),
+                BulletItem::Nested(unordered(vec![item("x")])),
+                BulletItem::Nested(ordered(vec![item("y")])),
+                item("b"),
+            ]))]
+        );
+    }
+
+    #[test]
+    fn numbers_without_marker_are_not_bullets() {
+        assert!(parse_block_as_bullets("1999 was a year").is_err());
+        assert!(parse_block_as_bullets("1.5 is a number").is_err());
+    }
+
+    #[test]
+    fn parse_table_1() {
+        let text =
+            "| left | **center** | right |\n| --- | :---: | ---: |\n| 1 | 2 |\n| a \\| b | c | d |";
+        let out = parse_block_as_table(text);
+        dbg!(&out);
+        let (rest, table) = out.unwrap();
+
+        let text = |x: &str| vec![Span::Text(x.into())];
+
+        let expected = Table::new(
+            vec![
+                text("left"),
+                vec![Span::Bold(vec![Span::Text("center".into())])],
+                text("right"),
+            ],
+            vec![Alignment::Left, Alignment::Center, Alignment::Right],
+            vec![
+                vec![text("1"), text("2"), vec![]],
+                vec![text("a | b"), text("c"), text("d")],
+            ],
+        );
+
+        assert_eq!(table, Block::Table(expected));
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn table_requires_delimiter_row() {
+        let text = "a | b\nc | d";
+        assert!(parse_block_as_table(text).is_err());
+
+        let text = "| a | b |\n| --- |";
+        assert!(parse_block_as_table(text).is_err());
+    }
+
+    #[test]
+    fn table_ends_at_a_different_row() {
+        let text = |x: &str| vec![Span::Text(x.into())];
+        let expected = |rows: Vec<Vec<Vec<Span>>>| {
+            Block::Table(Table::new(
+                vec![text("a"), text("b")],
+                vec![Alignment::Left, Alignment::Left],
+                rows,
+            ))
+        };
+
+        let markdown = "| a | b |\n|---|---|\n| 1 | 2 |\nsee a | b for more\n\nREST";
+        let (rest, table) = parse_block_as_table(markdown).unwrap();
+
+        assert_eq!(table, expected(vec![vec![text("1"), text("2")]]));
+        assert_eq!(rest, "see a | b for more\n\nREST");
+
+        // tables without outer pipes continue with rows like theirs
+        let markdown = "a | b\n--|--\n1 | 2\n3 | 4\n";
+        let (rest, table) = parse_block_as_table(markdown).unwrap();
+
+        assert_eq!(
+            table,
+            expected(vec![vec![text("1"), text("2")], vec![text("3"), text("4")]])
+        );
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn slide_with_table() {
+        let text = "
+            ## Slide Name
+
+            | a | b |
+            |---|---|
+            | 1 | 2 |
+
+            REST";
+
+        let slide = parse_slide(text);
+        dbg!(&slide);
+        let slide = slide.unwrap().1;
+
+        assert!(matches!(slide.contents[0], Block::Table(_)));
+        assert_eq!(
+            slide.contents[1],
+            Block::Paragraph(vec![Span::Text("REST".into())])
+        );
+    }
+
+    #[test]
+    fn unterminated_code_block() {
+        let text = "# Title\nAUTHOR=me\n\n## Slide\n\n```python\nprint()\n\n## Next\n";
+        let out = parse_markdown(text.as_bytes());
+
+        match out {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(diagnostic.kind, ParseErrorKind::UnterminatedCodeBlock);
+                assert_eq!(diagnostic.line, 6);
+                assert_eq!(diagnostic.column, 1);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_author() {
+        let text = "# Title\n\n## Slide\n";
+        let out = parse_markdown(text.as_bytes());
+
+        match out {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(diagnostic.kind, ParseErrorKind::MissingAuthor);
+                assert_eq!(diagnostic.line, 1);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
     #[test]
     fn get_header() {
         let text = r#"
@@ -614,7 +2620,215 @@ AUTHOR=Author Name
         let expected_title = vec![Span::Text("Presentation Title".to_string())];
 
         assert_eq!(first_slide.title, expected_title);
-        assert_eq!(first_slide.author, "Author Name");
+        assert_eq!(
+            first_slide.metadata.author,
+            vec![Author::new("Author Name".into())]
+        );
+    }
+
+    #[test]
+    fn header_from_front_matter() {
+        let text = "---\ntitle: Front **Title**\nauthor: Someone\n---\n\n## Slide\n";
+
+        let (rest, header) = parse_start_header(text).unwrap();
+        let expected_title = vec![
+            Span::Text("Front ".into()),
+            Span::Bold(vec![Span::Text("Title".into())]),
+        ];
+
+        assert_eq!(header.title, expected_title);
+        assert_eq!(header.metadata.author, vec![Author::new("Someone".into())]);
+        assert_eq!(rest, "\n## Slide\n");
+
+        // the markdown title and author are still allowed, front matter takes precedence
+        let text = "+++\nsubtitle = \"Sub\"\n+++\n# Title\nAUTHOR=me\n";
+        let (_, header) = parse_start_header(text).unwrap();
+
+        assert_eq!(header.title, vec![Span::Text("Title".into())]);
+        assert_eq!(header.subtitle, Some(vec![Span::Text("Sub".into())]));
+        assert_eq!(header.metadata.author, vec![Author::new("me".into())]);
+    }
+
+    #[test]
+    fn section_headings() {
+        let text =
+            "# Title\nAUTHOR=me\n\n# Intro\n\n## First\n\ntext\n\n# Details\n## Second\n\n# End\n";
+        let document = parse_markdown(text.as_bytes()).unwrap();
+
+        let expected = vec![
+            ParsedSection {
+                title: vec![Span::Text("Intro".into())],
+                slide: 0,
+            },
+            ParsedSection {
+                title: vec![Span::Text("Details".into())],
+                slide: 1,
+            },
+            ParsedSection {
+                title: vec![Span::Text("End".into())],
+                slide: 2,
+            },
+        ];
+
+        assert_eq!(document.slides.len(), 2);
+        assert_eq!(
+            document.slides[0].contents,
+            vec![Block::Paragraph(vec![Span::Text("text".into())])]
+        );
+        assert_eq!(document.sections, expected);
+    }
+
+    #[test]
+    fn text_outside_of_slides() {
+        for (text, line) in [
+            (
+                "# Title\nAUTHOR=me\n\nstray text\n\n# Intro\n\n## First\n",
+                4,
+            ),
+            ("# Title\nAUTHOR=me\n\n# Intro\n\n  stray\n\n## First\n", 6),
+        ] {
+            match parse_markdown(text.as_bytes()) {
+                Err(Error::Parse(diagnostic)) => {
+                    assert_eq!(diagnostic.kind, ParseErrorKind::TextOutsideSlide);
+                    assert_eq!(diagnostic.line, line);
+                }
+                other => panic!("unexpected output {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn subheadings_stay_in_slide() {
+        let text = "# Title\nAUTHOR=me\n\n## Slide\n\n### Sub *heading*\n\ntext\n\n#### Smaller\n\n## Next\n";
+        let document = parse_markdown(text.as_bytes()).unwrap();
+
+        let expected = vec![
+            Block::Heading {
+                level: 3,
+                text: vec![
+                    Span::Text("Sub ".into()),
+                    Span::Italics(vec![Span::Text("heading".into())]),
+                ],
+            },
+            Block::Paragraph(vec![Span::Text("text".into())]),
+            Block::Heading {
+                level: 4,
+                text: vec![Span::Text("Smaller".into())],
+            },
+        ];
+
+        assert_eq!(document.slides.len(), 2);
+        assert_eq!(document.slides[0].contents, expected);
+        assert_eq!(document.slides[1].title, vec![Span::Text("Next".into())]);
+    }
+
+    #[test]
+    fn callouts_and_quotes() {
+        let text = "> [!warning]\n> Careful\n>\n> > nested\n\n> [!EXAMPLE] Own title\n\n> plain";
+        let (_, blocks) = parse_block(text).unwrap();
+
+        let expected = vec![
+            Block::BeamerBlock(BeamerBlock {
+                kind: BlockKind::Alert,
+                title: vec![Span::Text("Warning".into())],
+                contents: vec![
+                    Block::Paragraph(vec![Span::Text("Careful".into())]),
+                    Block::Quote(vec![Block::Paragraph(vec![Span::Text("nested".into())])]),
+                ],
+            }),
+            Block::BeamerBlock(BeamerBlock {
+                kind: BlockKind::Example,
+                title: vec![Span::Text("Own title".into())],
+                contents: vec![],
+            }),
+            Block::Quote(vec![Block::Paragraph(vec![Span::Text("plain".into())])]),
+        ];
+
+        assert_eq!(blocks, expected);
+    }
+
+    #[test]
+    fn indented_quote_lines() {
+        let text = "> first line\n  > second line\n   >\n   > more";
+        let (_, blocks) = parse_block(text).unwrap();
+
+        let expected = vec![Block::Quote(vec![
+            Block::Paragraph(vec![Span::Text("first line\nsecond line".into())]),
+            Block::Paragraph(vec![Span::Text("more".into())]),
+        ])];
+
+        assert_eq!(blocks, expected);
+
+        let text = "# Title\nAUTHOR=me\n\n## Slide\n\n> quote\n  >\n  > $$\n  > x = 1\n";
+
+        match parse_markdown(text.as_bytes()) {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(diagnostic.kind, ParseErrorKind::UnterminatedEquation("$$"));
+                assert_eq!(diagnostic.line, 8);
+                assert_eq!(diagnostic.column, 5);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_inside_quotes() {
+        let text = "# Title\nAUTHOR=me\n\n## Slide\n\n> [!NOTE]\n> $$\n> x = 1\n";
+        let out = parse_markdown(text.as_bytes());
+
+        match out {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(diagnostic.kind, ParseErrorKind::UnterminatedEquation("$$"));
+                assert_eq!(diagnostic.line, 7);
+                assert_eq!(diagnostic.column, 3);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
+    #[test]
+    fn speaker_notes() {
+        let text =
+            "## Slide\n\ntext\n\n<!-- notes: first -->\n\nmore\n\n???\n\nsecond\n\n## Next\n";
+        let (rest, slide) = parse_slide(text).unwrap();
+
+        let paragraph = |text: &str| Block::Paragraph(vec![Span::Text(text.into())]);
+
+        assert_eq!(slide.contents, vec![paragraph("text"), paragraph("more")]);
+        assert_eq!(slide.notes, vec![paragraph("first"), paragraph("second")]);
+        assert_eq!(rest, "## Next\n");
+    }
+
+    #[test]
+    fn code_in_notes() {
+        let text =
+            "# Title\nAUTHOR=me\n\n## Slide\n\ntext\n\n???\n\n> ```rust\n> let x = 1;\n> ```\n";
+
+        match parse_markdown(text.as_bytes()) {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(diagnostic.kind, ParseErrorKind::CodeInNotes);
+                assert_eq!(diagnostic.line, 10);
+                assert_eq!(diagnostic.column, 3);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unterminated_front_matter() {
+        let text = "---\ntitle: Title\n\n## Slide\n";
+        let out = parse_markdown(text.as_bytes());
+
+        match out {
+            Err(Error::Parse(diagnostic)) => {
+                assert_eq!(
+                    diagnostic.kind,
+                    ParseErrorKind::UnterminatedFrontMatter("---")
+                );
+                assert_eq!(diagnostic.line, 1);
+            }
+            other => panic!("unexpected output {:?}", other),
+        }
     }
 
     #[test]
@@ -669,7 +2883,7 @@ energy = energy * dx * dy * dz
         let out = out.unwrap();
 
         for c in out.1.contents {
-            assert_eq!(matches!(c, Block::Picture(_)), false)
+            assert!(!matches!(c, Block::Picture(_)))
         }
     }
 
@@ -727,7 +2941,7 @@ energy = energy * dx * dy * dz
         let out = parse_as_code(code);
         dbg!(&out);
         let out = out.unwrap().1;
-        assert_eq!(matches!(out, Block::Code(_)), true);
+        assert!(matches!(out, Block::Code(_)));
     }
 
     #[test]
@@ -792,7 +3006,7 @@ energy = energy * dx * dy * dz
             ```
         ";
 
-        let output = parse_slide(&slide);
+        let output = parse_slide(slide);
         dbg!(&output);
 
         output.unwrap();
@@ -849,4 +3063,179 @@ energy = energy * dx * dy * dz
 
         assert_eq!(&expected, output);
     }
+
+    #[test]
+    fn picture_columns_directive() {
+        let text = "
+            ## Comparison
+
+            %PICTURECOLUMNS=3
+            ![before](./before.png)
+            ![after](./after.png)";
+
+        let (_, slide) = parse_slide(text).unwrap();
+
+        assert_eq!(
+            slide.contents[0],
+            Block::Directive(Directive::PictureColumns(3))
+        );
+        assert_eq!(slide.contents.len(), 3);
+
+        let error = parse_as_directive("%PICTURECOLUMNS=0\n").unwrap_err();
+        assert!(matches!(
+            error,
+            nom::Err::Failure(ParseError {
+                kind: ParseErrorKind::InvalidPictureColumns,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn column_directives() {
+        let text = "
+            ## Comparison
+
+            %COLUMNS=CENTER,2,1
+
+            left
+
+            %COLUMN
+
+            right
+
+            %ENDCOLUMNS";
+
+        let (_, slide) = parse_slide(text).unwrap();
+
+        let expected = vec![
+            Block::Directive(Directive::Columns {
+                alignment: ColumnAlignment::Center,
+                ratios: vec![2.0, 1.0],
+            }),
+            Block::Paragraph(vec![Span::Text("left".into())]),
+            Block::Directive(Directive::Column),
+            Block::Paragraph(vec![Span::Text("right".into())]),
+            Block::Directive(Directive::EndColumns),
+        ];
+
+        assert_eq!(slide.contents, expected);
+
+        let (_, directive) = parse_as_directive("%COLUMNS\n").unwrap();
+        assert_eq!(
+            directive,
+            Block::Directive(Directive::Columns {
+                alignment: ColumnAlignment::Top,
+                ratios: vec![],
+            })
+        );
+
+        let error = parse_as_directive("%COLUMNS=0.5,wide\n").unwrap_err();
+        assert!(matches!(
+            error,
+            nom::Err::Failure(ParseError {
+                kind: ParseErrorKind::InvalidColumnOption(option),
+                input: "wide",
+            }) if option == "wide"
+        ));
+    }
+
+    #[test]
+    fn column_mistakes() {
+        let error = parse_as_directive("%COLUMNS=0.7,0.7\n").unwrap_err();
+        assert!(matches!(
+            error,
+            nom::Err::Failure(ParseError {
+                kind: ParseErrorKind::InvalidColumnRatios(ratios),
+                ..
+            }) if ratios == "0.7,0.7"
+        ));
+
+        // relative widths can add up to anything
+        assert!(parse_as_directive("%COLUMNS=0.5,0.5\n").is_ok());
+        assert!(parse_as_directive("%COLUMNS=2,1\n").is_ok());
+
+        let cases = [
+            ("%COLUMNS\n\nleft\n", ParseErrorKind::UnclosedColumns, 3),
+            (
+                "%COLUMNS\n\nleft\n\n%NEWSLIDE\n\n%ENDCOLUMNS\n",
+                ParseErrorKind::UnclosedColumns,
+                3,
+            ),
+            (
+                "text\n\n%COLUMN\n\nmore\n",
+                ParseErrorKind::StrayColumnDirective("%COLUMN"),
+                5,
+            ),
+            (
+                "%COLUMNS\n\n%ENDCOLUMNS\n\n%ENDCOLUMNS\n",
+                ParseErrorKind::StrayColumnDirective("%ENDCOLUMNS"),
+                7,
+            ),
+        ];
+
+        for (slide, kind, line) in cases {
+            let text = format!("# Title\nAUTHOR=me\n\n## Slide\n\n{}", slide);
+
+            match parse_markdown(text.as_bytes()) {
+                Err(Error::Parse(diagnostic)) => {
+                    assert_eq!(diagnostic.kind, kind);
+                    assert_eq!(diagnostic.line, line + 3);
+                    assert_eq!(diagnostic.column, 1);
+                }
+                other => panic!("unexpected output {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn picture_side_and_split() {
+        let text = "![](somepicture)\n%LEFT\n%SPLIT=0.3\n\n";
+        let (_, picture) = parse_block_as_picture(text).unwrap();
+
+        let expected = ParsePicture {
+            path: "somepicture".to_string(),
+            caption: None,
+            directive: Some(vec![
+                PictureDirective::Left,
+                PictureDirective::Split {
+                    text: 0.3,
+                    picture: 1.0 - SPLIT_GAP - 0.3,
+                },
+            ]),
+        };
+
+        assert_eq!(picture, Block::Picture(expected));
+
+        let (_, directive) = picture_directive("%SPLIT=0.5, 0.45\n").unwrap();
+        assert_eq!(
+            directive,
+            PictureDirective::Split {
+                text: 0.5,
+                picture: 0.45
+            }
+        );
+
+        for invalid in [
+            "%SPLIT=0.6,0.5\n",
+            "%SPLIT=1.2\n",
+            "%SPLIT=half\n",
+            "%SPLIT=NaN\n",
+            "%SPLIT=0.5,NaN\n",
+            "%SPLIT=-inf\n",
+        ] {
+            let error = picture_directive(invalid).unwrap_err();
+            assert!(
+                matches!(
+                    error,
+                    nom::Err::Failure(ParseError {
+                        kind: ParseErrorKind::InvalidSplit(_),
+                        ..
+                    })
+                ),
+                "{}",
+                invalid
+            );
+        }
+    }
 }
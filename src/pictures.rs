@@ -0,0 +1,192 @@
+use super::diagnostic::{Diagnostic, ParseError, ParseErrorKind};
+use super::parse::Document;
+
+use std::path::{Component, Path, PathBuf};
+
+/// extensions that pdflatex can include with `\includegraphics`. eps pictures are converted
+/// with `epstopdf` on the fly
+const SUPPORTED_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "jbig2", "jb2", "mps", "eps"];
+
+/// find every picture relative to the markdown file and rewrite its path relative to the
+/// directory of the `.tex` file. returns the files of the pictures
+pub(crate) fn resolve(
+    document: &mut Document,
+    markdown: &str,
+    markdown_directory: &Path,
+    output_directory: &Path,
+) -> Result<Vec<PathBuf>, Diagnostic> {
+    // without an output directory to be relative to, the paths are absolute
+    let output_directory = std::fs::canonicalize(output_directory).ok();
+    let mut files = Vec::new();
+
+    for picture in document.pictures_mut() {
+        let path = Path::new(&picture.path);
+
+        let (file, latex_file) = find_picture(&markdown_directory.join(path)).map_err(|kind| {
+            let error = ParseError {
+                input: locate(markdown, &picture.path),
+                kind,
+            };
+            Diagnostic::new(markdown, error)
+        })?;
+
+        picture.path = latex_path(&latex_file, output_directory.as_deref());
+        files.push(file);
+    }
+
+    Ok(files)
+}
+
+/// the file of a picture and the absolute path that latex should include. Without an extension
+/// latex tries every one it knows about, so any picture with a supported extension will do
+fn find_picture(path: &Path) -> Result<(PathBuf, PathBuf), ParseErrorKind> {
+    let missing = || ParseErrorKind::MissingPicture(path.display().to_string());
+
+    let file = match path.extension() {
+        Some(extension) => {
+            let extension = extension.to_string_lossy().to_lowercase();
+
+            if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+                return Err(ParseErrorKind::UnsupportedPicture(extension));
+            }
+
+            path.to_path_buf()
+        }
+        None => SUPPORTED_EXTENSIONS
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|file| file.is_file())
+            .ok_or_else(missing)?,
+    };
+
+    if !file.is_file() {
+        return Err(missing());
+    }
+
+    let absolute = std::fs::canonicalize(&file).map_err(|_| missing())?;
+
+    let latex_file = if path.extension().is_some() {
+        absolute
+    } else {
+        absolute.with_extension("")
+    };
+
+    Ok((file, latex_file))
+}
+
+/// `file` relative to `directory` when it has one, with the `/` separators latex expects
+fn latex_path(file: &Path, directory: Option<&Path>) -> String {
+    let path = directory
+        .and_then(|directory| relative_path(directory, file))
+        .unwrap_or_else(|| file.to_path_buf());
+
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// path from `directory` to `file`, both of which are absolute. `None` if they are on different
+/// drives
+fn relative_path(directory: &Path, file: &Path) -> Option<PathBuf> {
+    let directory: Vec<Component> = directory.components().collect();
+    let file: Vec<Component> = file.components().collect();
+
+    if directory.first() != file.first() {
+        return None;
+    }
+
+    let common = directory
+        .iter()
+        .zip(file.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+
+    for _ in common..directory.len() {
+        relative.push("..");
+    }
+
+    for component in &file[common..] {
+        relative.push(component);
+    }
+
+    Some(relative)
+}
+
+/// the path of the first picture in the markdown with this path, which is the picture the error
+/// is about since every picture before it was found. Links to the same path are skipped
+fn locate<'a>(markdown: &'a str, path: &str) -> &'a str {
+    let is_picture = |idx: usize| {
+        let line_start = markdown[..idx].rfind('\n').map_or(0, |start| start + 1);
+        let line = &markdown[line_start..idx];
+
+        // the `![` that opens this `](path)`, without another link or picture in between
+        line.rfind("![")
+            .is_some_and(|start| !line[start..].contains("]("))
+    };
+
+    match markdown
+        .match_indices(&format!("]({})", path))
+        .find(|(idx, _)| is_picture(*idx))
+    {
+        Some((idx, _)) => &markdown[idx + 2..],
+        None => &markdown[markdown.len()..],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_markdown;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn relative_paths() {
+        let relative = relative_path(Path::new("/a/out"), Path::new("/a/figs/plot.png"));
+        assert_eq!(relative, Some(PathBuf::from("../figs/plot.png")));
+
+        let relative = relative_path(Path::new("/a"), Path::new("/a/plot.png"));
+        assert_eq!(relative, Some(PathBuf::from("plot.png")));
+    }
+
+    #[test]
+    fn missing_and_unsupported_pictures() {
+        let directory = TempDir::new("pictures");
+        std::fs::create_dir_all(directory.join("figs")).unwrap();
+        std::fs::write(directory.join("figs/plot.png"), b"").unwrap();
+
+        let markdown =
+            "# Title\nAUTHOR=Author\n\n## Plots\n\n![](figs/plot.png)\n\n![](figs/plot)\n";
+        let mut document = parse_markdown(markdown.as_bytes()).unwrap();
+        let files = resolve(&mut document, markdown, &directory, &directory).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert!(document
+            .pictures_mut()
+            .iter()
+            .all(|picture| picture.path.starts_with("figs/plot")));
+
+        let markdown = "# Title\nAUTHOR=Author\n\n## Plots\n\n![](figs/missing.png)\n";
+        let mut document = parse_markdown(markdown.as_bytes()).unwrap();
+        let error = resolve(&mut document, markdown, &directory, &directory).unwrap_err();
+
+        assert!(matches!(error.kind, ParseErrorKind::MissingPicture(_)));
+        assert_eq!(error.line, 6);
+        assert_eq!(error.column, 5);
+
+        let markdown = "# Title\nAUTHOR=Author\n\n## Plots\n\n![](figs/plot.gif)\n";
+        let mut document = parse_markdown(markdown.as_bytes()).unwrap();
+        let error = resolve(&mut document, markdown, &directory, &directory).unwrap_err();
+
+        assert_eq!(error.kind, ParseErrorKind::UnsupportedPicture("gif".into()));
+
+        // a link to the same path before the picture is not where the error is
+        let markdown =
+            "# Title\nAUTHOR=Author\n\n## Plots\n\nsee [the plot](figs/b.png)\n\n![Cap](figs/b.png)\n";
+        let mut document = parse_markdown(markdown.as_bytes()).unwrap();
+        let error = resolve(&mut document, markdown, &directory, &directory).unwrap_err();
+
+        assert!(matches!(error.kind, ParseErrorKind::MissingPicture(_)));
+        assert_eq!(error.line, 8);
+        assert_eq!(error.column, 8);
+    }
+}
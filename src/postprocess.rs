@@ -1,36 +1,391 @@
-use super::data::{ContentOptions, Presentation, Slide};
-use super::parse::{Block, Directive, Document, ParsedSlide};
+use super::data::{ContentOptions, Pictures, Presentation, Section, Slide};
+use super::metadata::SubheadingStyle;
+use super::parse::{
+    normalize_link_label, BeamerBlock, Block, BlockKind, BulletItem, BulletList, Column,
+    ColumnAlignment, Columns, Directive, Document, ParsedSlide, Span,
+};
 
-pub(crate) fn postprocess(presentation: Document, ignore_newslide: bool) -> Presentation {
-    let slides = presentation
-        .slides
-        .into_iter()
-        .map(|x| process_slide(x, ignore_newslide))
-        .flatten()
-        .collect::<Vec<_>>();
+use std::collections::HashMap;
+
+pub(crate) fn postprocess(mut presentation: Document, ignore_newslide: bool) -> Presentation {
+    resolve_reference_links(&mut presentation);
+
+    let subheadings = presentation.first.metadata.subheadings;
+
+    let mut slides = Vec::new();
+    let mut sections = Vec::new();
+    let mut parsed_sections = presentation.sections.into_iter().peekable();
+
+    for (idx, slide) in presentation.slides.into_iter().enumerate() {
+        // a single slide may become several, so sections point to the slides after processing
+        while let Some(section) = parsed_sections.next_if(|section| section.slide == idx) {
+            sections.push(Section {
+                title: section.title.into(),
+                slide: slides.len(),
+            });
+        }
+
+        slides.extend(process_slide(slide, ignore_newslide, subheadings));
+    }
+
+    // sections without any slides after them
+    sections.extend(parsed_sections.map(|section| Section {
+        title: section.title.into(),
+        slide: slides.len(),
+    }));
 
     Presentation {
         title: presentation.first.title.into(),
-        author: presentation.first.author,
+        subtitle: presentation.first.subtitle.map(Into::into),
+        metadata: presentation.first.metadata,
         slides,
+        sections,
     }
 }
 
-fn process_slide(slide: ParsedSlide, ignore_newslide: bool) -> Vec<Slide> {
+fn process_slide(
+    slide: ParsedSlide,
+    ignore_newslide: bool,
+    subheadings: SubheadingStyle,
+) -> Vec<Slide> {
     // first organize the slides based on the directives
     let slides = text_directive_handler(slide.contents, ignore_newslide);
 
     // then map each of the slides into either plain text or a text with picture
     slides
         .into_iter()
+        .map(mark_incremental_lists)
+        .map(group_columns)
         .map(to_content_options)
+        .map(|contents| match subheadings {
+            SubheadingStyle::Block => group_subheadings(contents),
+            SubheadingStyle::Bold => contents,
+        })
+        // every copy of a slide split by %NEWSLIDE has the same notes
         .map(|contents| Slide {
             title: slide.title.clone().into(),
             contents,
+            notes: slide.notes.clone(),
         })
         .collect()
 }
 
+/// every `###` heading and the text after it, up to the next `###`, become a beamer block
+fn group_subheadings(contents: ContentOptions) -> ContentOptions {
+    match contents {
+        ContentOptions::OnlyText(text) => ContentOptions::OnlyText(group_headings(text)),
+        ContentOptions::TextAndPicture(text, pictures) => {
+            ContentOptions::TextAndPicture(group_headings(text), pictures)
+        }
+        ContentOptions::OnlyPicture(pictures) => ContentOptions::OnlyPicture(pictures),
+    }
+}
+
+fn group_headings(blocks: Vec<Block>) -> Vec<Block> {
+    let mut out = Vec::new();
+    let mut in_group = false;
+
+    for block in blocks {
+        match (block, out.last_mut()) {
+            (Block::Heading { level: 3, text }, _) => {
+                out.push(Block::BeamerBlock(BeamerBlock {
+                    kind: BlockKind::Block,
+                    title: text,
+                    contents: Vec::new(),
+                }));
+                in_group = true;
+            }
+            // a heading inside of a column only groups the blocks of its column
+            (Block::Columns(mut columns), _) => {
+                for column in columns.columns.iter_mut() {
+                    column.contents = group_headings(std::mem::take(&mut column.contents));
+                }
+                out.push(Block::Columns(columns));
+                in_group = false;
+            }
+            (block, Some(Block::BeamerBlock(group))) if in_group => group.contents.push(block),
+            (block, _) => out.push(block),
+        }
+    }
+
+    out
+}
+
+/// move the blocks between `%COLUMNS` and `%ENDCOLUMNS` into columns. Blocks before the first
+/// `%COLUMN` belong to the first column. Parsing already made sure that every `%COLUMNS` is closed
+fn group_columns(blocks: Vec<Block>) -> Vec<Block> {
+    let mut out = Vec::new();
+    // the columns being built and the width ratios of the `%COLUMNS` that started them
+    let mut current: Option<(ColumnAlignment, Vec<f64>, Vec<Vec<Block>>)> = None;
+
+    for block in blocks {
+        match (block, current.as_mut()) {
+            (Block::Directive(Directive::Columns { alignment, ratios }), _) => {
+                if let Some(columns) = current.take() {
+                    out.push(build_columns(columns));
+                }
+                current = Some((alignment, ratios, vec![Vec::new()]));
+            }
+            (Block::Directive(Directive::Column), Some((_, _, columns))) => {
+                // the blocks before the first `%COLUMN` are already in the first column
+                if !(columns.len() == 1 && columns[0].is_empty()) {
+                    columns.push(Vec::new());
+                }
+            }
+            (Block::Directive(Directive::EndColumns), _) => {
+                if let Some(columns) = current.take() {
+                    out.push(build_columns(columns));
+                }
+            }
+            // a `%COLUMN` outside of `%COLUMNS` has nothing to do
+            (Block::Directive(Directive::Column), None) => (),
+            (block, Some((_, _, columns))) => columns.last_mut().unwrap().push(block),
+            (block, None) => out.push(block),
+        }
+    }
+
+    if let Some(columns) = current {
+        out.push(build_columns(columns));
+    }
+
+    out
+}
+
+fn build_columns(
+    (alignment, ratios, columns): (ColumnAlignment, Vec<f64>, Vec<Vec<Block>>),
+) -> Block {
+    let listed = ratios.len().min(columns.len());
+    let unlisted = columns.len() - listed;
+    let left = 1.0 - ratios[..listed].iter().sum::<f64>();
+
+    // ratios below 1 are fractions of the slide, and the other columns share what is left of it.
+    // Anything else is a relative width, where columns without a ratio get the average of the
+    // others
+    let widths: Vec<f64> = if !ratios.is_empty()
+        && ratios.iter().all(|ratio| *ratio < 1.0)
+        && (unlisted == 0 || left > 0.0)
+    {
+        (0..columns.len())
+            .map(|idx| match ratios.get(idx) {
+                Some(ratio) => *ratio,
+                None => left / unlisted as f64,
+            })
+            .collect()
+    } else {
+        let default = if ratios.is_empty() {
+            1.0
+        } else {
+            ratios.iter().sum::<f64>() / ratios.len() as f64
+        };
+
+        let weights: Vec<f64> = (0..columns.len())
+            .map(|idx| ratios.get(idx).copied().unwrap_or(default))
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        weights.into_iter().map(|weight| weight / total).collect()
+    };
+
+    Block::Columns(Columns {
+        alignment,
+        columns: columns
+            .into_iter()
+            .zip(widths)
+            .map(|(contents, width)| Column { width, contents })
+            .collect(),
+    })
+}
+
+/// replace every `[text][label]` link with the url from its `[label]: url` definition, which may
+/// be on any slide of the document
+fn resolve_reference_links(document: &mut Document) {
+    let mut definitions = HashMap::new();
+
+    for slide in document.slides.iter_mut() {
+        take_definitions(&mut slide.contents, &mut definitions);
+        take_definitions(&mut slide.notes, &mut definitions);
+    }
+
+    resolve_spans(&mut document.first.title, &definitions);
+
+    if let Some(subtitle) = document.first.subtitle.as_mut() {
+        resolve_spans(subtitle, &definitions);
+    }
+
+    for section in document.sections.iter_mut() {
+        resolve_spans(&mut section.title, &definitions);
+    }
+
+    for slide in document.slides.iter_mut() {
+        resolve_spans(&mut slide.title, &definitions);
+
+        for block in slide.contents.iter_mut().chain(slide.notes.iter_mut()) {
+            resolve_block(block, &definitions);
+        }
+    }
+}
+
+/// remove every `[label]: url` from the blocks, including the ones nested in quotes, callouts,
+/// columns and notes
+fn take_definitions(blocks: &mut Vec<Block>, definitions: &mut HashMap<String, String>) {
+    blocks.retain_mut(|block| match block {
+        Block::LinkDefinition { label, url } => {
+            // the first definition of a label takes precedence
+            definitions
+                .entry(normalize_link_label(label))
+                .or_insert_with(|| url.clone());
+            false
+        }
+        Block::BeamerBlock(group) => {
+            take_definitions(&mut group.contents, definitions);
+            true
+        }
+        Block::Quote(blocks) | Block::Notes(blocks) => {
+            take_definitions(blocks, definitions);
+            true
+        }
+        Block::Columns(columns) => {
+            for column in columns.columns.iter_mut() {
+                take_definitions(&mut column.contents, definitions);
+            }
+            true
+        }
+        _ => true,
+    });
+}
+
+fn resolve_block(block: &mut Block, definitions: &HashMap<String, String>) {
+    match block {
+        Block::Paragraph(spans) => resolve_spans(spans, definitions),
+        Block::BulletedList(list) => resolve_list(list, definitions),
+        Block::Table(table) => table
+            .cells_mut()
+            .for_each(|cell| resolve_spans(cell, definitions)),
+        Block::Heading { text, .. } => resolve_spans(text, definitions),
+        Block::BeamerBlock(group) => {
+            resolve_spans(&mut group.title, definitions);
+
+            for block in group.contents.iter_mut() {
+                resolve_block(block, definitions);
+            }
+        }
+        Block::Quote(blocks) | Block::Notes(blocks) => {
+            for block in blocks.iter_mut() {
+                resolve_block(block, definitions);
+            }
+        }
+        Block::Columns(columns) => {
+            for block in columns
+                .columns
+                .iter_mut()
+                .flat_map(|column| column.contents.iter_mut())
+            {
+                resolve_block(block, definitions);
+            }
+        }
+        Block::Picture(_)
+        | Block::Code(_)
+        | Block::Latex(_)
+        | Block::Equation(_)
+        | Block::Directive(_)
+        | Block::LinkDefinition { .. } => (),
+    }
+}
+
+fn resolve_list(list: &mut BulletList, definitions: &HashMap<String, String>) {
+    for item in list.items.iter_mut() {
+        match item {
+            BulletItem::Single(spans) => resolve_spans(spans, definitions),
+            BulletItem::Nested(list) => resolve_list(list, definitions),
+        }
+    }
+}
+
+fn resolve_spans(spans: &mut [Span], definitions: &HashMap<String, String>) {
+    for span in spans.iter_mut() {
+        match span {
+            Span::Bold(inner)
+            | Span::Italics(inner)
+            | Span::Strikethrough(inner)
+            | Span::Link { text: inner, .. } => resolve_spans(inner, definitions),
+            Span::ReferenceLink { text, label, .. } => {
+                resolve_spans(text, definitions);
+
+                if let Some(url) = definitions.get(&normalize_link_label(label)) {
+                    *span = Span::Link {
+                        text: std::mem::take(text),
+                        url: url.clone(),
+                    };
+                }
+            }
+            Span::Text(_)
+            | Span::Equation(_)
+            | Span::Math(_)
+            | Span::Code(_)
+            | Span::Url(_)
+            | Span::Latex(_) => (),
+        }
+    }
+}
+
+/// `%INCREMENTAL` at the start of a slide reveals every list of the slide one item at a time,
+/// anywhere else only the next list
+fn mark_incremental_lists(blocks: Vec<Block>) -> Vec<Block> {
+    let slide_wide = matches!(
+        blocks.first(),
+        Some(Block::Directive(Directive::Incremental))
+    );
+    let mut next_list = false;
+
+    let mut blocks: Vec<Block> = blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Directive(Directive::Incremental) => {
+                next_list = true;
+                None
+            }
+            Block::BulletedList(mut list) if next_list => {
+                set_incremental(&mut list);
+                next_list = false;
+                Some(Block::BulletedList(list))
+            }
+            block => Some(block),
+        })
+        .collect();
+
+    if slide_wide {
+        set_incremental_blocks(&mut blocks);
+    }
+
+    blocks
+}
+
+fn set_incremental_blocks(blocks: &mut [Block]) {
+    for block in blocks.iter_mut() {
+        match block {
+            Block::BulletedList(list) => set_incremental(list),
+            Block::BeamerBlock(group) => set_incremental_blocks(&mut group.contents),
+            Block::Quote(blocks) => set_incremental_blocks(blocks),
+            Block::Columns(columns) => {
+                for column in columns.columns.iter_mut() {
+                    set_incremental_blocks(&mut column.contents);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn set_incremental(list: &mut BulletList) {
+    list.incremental = true;
+
+    for item in list.items.iter_mut() {
+        if let BulletItem::Nested(nested) = item {
+            set_incremental(nested);
+        }
+    }
+}
+
 /// this function cannot be called with a picture
 fn text_directive_handler(contents: Vec<Block>, ignore_newslide: bool) -> Vec<Vec<Block>> {
     let mut current_slide_contents = Vec::new();
@@ -53,6 +408,8 @@ fn text_directive_handler(contents: Vec<Block>, ignore_newslide: bool) -> Vec<Ve
                         current_slide_contents = tmp_contents;
                     }
                 }
+                // overlays stay in the slide, they are handled while writing the latex
+                directive => current_slide_contents.push(Block::Directive(directive)),
             }
         } else {
             // we have no new directives, just add the
@@ -75,6 +432,12 @@ fn is_only_text(current_contents: &[Block]) -> bool {
     true
 }
 
+fn has_columns(current_contents: &[Block]) -> bool {
+    current_contents
+        .iter()
+        .any(|block| matches!(block, Block::Columns(_)))
+}
+
 fn is_only_picture(current_contents: &[Block]) -> bool {
     for c in current_contents {
         if !matches!(c, Block::Picture(_)) {
@@ -86,34 +449,50 @@ fn is_only_picture(current_contents: &[Block]) -> bool {
 
 // convert a slide into its renderable formk
 fn to_content_options(slide_content: Vec<Block>) -> ContentOptions {
-    if is_only_text(&slide_content) {
+    // the last %PICTURECOLUMNS of the slide wins
+    let mut columns = None;
+    let slide_content: Vec<Block> = slide_content
+        .into_iter()
+        .filter(|block| match block {
+            Block::Directive(Directive::PictureColumns(count)) => {
+                columns = Some(*count);
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    // explicit columns replace the automatic split of text and pictures
+    if is_only_text(&slide_content) || has_columns(&slide_content) {
         ContentOptions::OnlyText(slide_content)
     } else if is_only_picture(&slide_content) {
-        // grab the first picture in the slides
-        let picture = match slide_content.into_iter().next().unwrap() {
-            Block::Picture(x) => x,
-            _ => unreachable!(),
-        };
-
-        ContentOptions::OnlyPicture(picture.into())
+        ContentOptions::OnlyPicture(collect_pictures(slide_content, columns))
     } else {
-        let (picture, text): (Vec<_>, Vec<_>) = slide_content
+        let (pictures, text): (Vec<_>, Vec<_>) = slide_content
             .into_iter()
             .partition(|x| matches!(x, Block::Picture(_)));
 
-        let picture = match picture.into_iter().next().unwrap() {
-            Block::Picture(x) => x,
+        ContentOptions::TextAndPicture(text, collect_pictures(pictures, columns))
+    }
+}
+
+fn collect_pictures(blocks: Vec<Block>, columns: Option<usize>) -> Pictures {
+    let pictures = blocks
+        .into_iter()
+        .map(|block| match block {
+            Block::Picture(picture) => picture.into(),
             _ => unreachable!(),
-        };
+        })
+        .collect();
 
-        ContentOptions::TextAndPicture(text, picture.into())
-    }
+    Pictures::new(pictures, columns)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parse::Span;
+    use crate::metadata::Metadata;
+    use crate::parse::{ListKind, ParsePicture, ParsedSection, ParsedTitle, ReferenceKind};
 
     fn spans_from_text(text: &str) -> Vec<Span> {
         vec![Span::Text(text.to_string())]
@@ -123,6 +502,176 @@ mod tests {
         Block::Paragraph(spans_from_text(text))
     }
 
+    #[test]
+    fn reference_links_resolve_across_slides() {
+        let link = |label: &str| Span::ReferenceLink {
+            text: spans_from_text("text"),
+            label: label.into(),
+            kind: ReferenceKind::Full,
+        };
+
+        let mut document = Document {
+            first: ParsedTitle {
+                title: spans_from_text("title"),
+                subtitle: None,
+                metadata: Metadata::default(),
+            },
+            slides: vec![
+                ParsedSlide {
+                    title: spans_from_text("1"),
+                    contents: vec![Block::Paragraph(vec![link("Some  Ref"), link("missing")])],
+                    notes: vec![],
+                },
+                ParsedSlide {
+                    title: spans_from_text("2"),
+                    contents: vec![Block::LinkDefinition {
+                        label: "some ref".into(),
+                        url: "https://example.com".into(),
+                    }],
+                    notes: vec![],
+                },
+            ],
+            sections: vec![],
+        };
+
+        resolve_reference_links(&mut document);
+
+        let expected = vec![Block::Paragraph(vec![
+            Span::Link {
+                text: spans_from_text("text"),
+                url: "https://example.com".into(),
+            },
+            link("missing"),
+        ])];
+
+        assert_eq!(document.slides[0].contents, expected);
+        assert!(document.slides[1].contents.is_empty());
+    }
+
+    #[test]
+    fn sections_follow_split_slides() {
+        let slide = |contents| ParsedSlide {
+            title: spans_from_text("slide"),
+            contents,
+            notes: vec![],
+        };
+        let section = |title, slide| ParsedSection {
+            title: spans_from_text(title),
+            slide,
+        };
+
+        let document = Document {
+            first: ParsedTitle {
+                title: spans_from_text("title"),
+                subtitle: None,
+                metadata: Metadata::default(),
+            },
+            slides: vec![
+                slide(vec![
+                    paragraph("1"),
+                    Block::Directive(Directive::NewSlide),
+                    paragraph("2"),
+                ]),
+                slide(vec![paragraph("3")]),
+            ],
+            sections: vec![section("a", 0), section("b", 1), section("c", 2)],
+        };
+
+        let presentation = postprocess(document, false);
+        let slides: Vec<usize> = presentation.sections.iter().map(|s| s.slide).collect();
+
+        assert_eq!(presentation.slides.len(), 3);
+        assert_eq!(slides, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn subheadings_become_blocks() {
+        let heading = |level, text| Block::Heading {
+            level,
+            text: spans_from_text(text),
+        };
+
+        let contents = ContentOptions::OnlyText(vec![
+            paragraph("before"),
+            heading(3, "first"),
+            paragraph("1"),
+            heading(4, "small"),
+            heading(3, "second"),
+            paragraph("2"),
+        ]);
+
+        let expected = ContentOptions::OnlyText(vec![
+            paragraph("before"),
+            Block::BeamerBlock(BeamerBlock {
+                kind: BlockKind::Block,
+                title: spans_from_text("first"),
+                contents: vec![paragraph("1"), heading(4, "small")],
+            }),
+            Block::BeamerBlock(BeamerBlock {
+                kind: BlockKind::Block,
+                title: spans_from_text("second"),
+                contents: vec![paragraph("2")],
+            }),
+        ]);
+
+        assert_eq!(group_subheadings(contents), expected);
+    }
+
+    #[test]
+    fn incremental_lists() {
+        let list = || {
+            Block::BulletedList(BulletList {
+                kind: ListKind::Unordered,
+                items: vec![BulletItem::Single(spans_from_text("item"))],
+                incremental: false,
+            })
+        };
+        let incremental = || {
+            Block::BulletedList(BulletList {
+                kind: ListKind::Unordered,
+                items: vec![BulletItem::Single(spans_from_text("item"))],
+                incremental: true,
+            })
+        };
+
+        let blocks = vec![
+            paragraph("1"),
+            Block::Directive(Directive::Incremental),
+            list(),
+            Block::Directive(Directive::Pause),
+            list(),
+        ];
+
+        let expected = vec![
+            paragraph("1"),
+            incremental(),
+            Block::Directive(Directive::Pause),
+            list(),
+        ];
+
+        assert_eq!(mark_incremental_lists(blocks), expected);
+
+        let blocks = vec![Block::Directive(Directive::Incremental), list(), list()];
+
+        assert_eq!(
+            mark_incremental_lists(blocks),
+            vec![incremental(), incremental()]
+        );
+
+        let columns = |list: Block| {
+            Block::Columns(Columns {
+                alignment: ColumnAlignment::Top,
+                columns: vec![Column {
+                    width: 1.0,
+                    contents: vec![list],
+                }],
+            })
+        };
+        let blocks = vec![Block::Directive(Directive::Incremental), columns(list())];
+
+        assert_eq!(mark_incremental_lists(blocks), vec![columns(incremental())]);
+    }
+
     #[test]
     fn simple_newslide_directive() {
         let blocks = vec![
@@ -140,4 +689,187 @@ mod tests {
         assert_eq!(out_blocks[0], slide_1);
         assert_eq!(out_blocks[1], slide_2);
     }
+
+    #[test]
+    fn every_picture_is_kept() {
+        let picture = |path: &str| {
+            Block::Picture(ParsePicture {
+                path: path.into(),
+                caption: None,
+                directive: None,
+            })
+        };
+        let pictures = |columns| {
+            Pictures::new(
+                vec![
+                    ParsePicture {
+                        path: "a.png".into(),
+                        caption: None,
+                        directive: None,
+                    }
+                    .into(),
+                    ParsePicture {
+                        path: "b.png".into(),
+                        caption: None,
+                        directive: None,
+                    }
+                    .into(),
+                ],
+                columns,
+            )
+        };
+
+        let blocks = vec![picture("a.png"), picture("b.png")];
+        assert_eq!(
+            to_content_options(blocks),
+            ContentOptions::OnlyPicture(pictures(None))
+        );
+
+        let blocks = vec![
+            Block::Directive(Directive::PictureColumns(1)),
+            picture("a.png"),
+            paragraph("text"),
+            picture("b.png"),
+        ];
+        assert_eq!(
+            to_content_options(blocks),
+            ContentOptions::TextAndPicture(vec![paragraph("text")], pictures(Some(1)))
+        );
+    }
+
+    #[test]
+    fn columns_from_directives() {
+        let blocks = vec![
+            paragraph("before"),
+            Block::Directive(Directive::Columns {
+                alignment: ColumnAlignment::Top,
+                ratios: vec![0.6],
+            }),
+            paragraph("1"),
+            Block::Directive(Directive::Column),
+            paragraph("2"),
+            Block::Directive(Directive::Column),
+            paragraph("3"),
+            Block::Directive(Directive::EndColumns),
+            paragraph("after"),
+        ];
+
+        let columns = match &group_columns(blocks)[..] {
+            [before, Block::Columns(columns), after] => {
+                assert_eq!(before, &paragraph("before"));
+                assert_eq!(after, &paragraph("after"));
+                columns.clone()
+            }
+            other => panic!("unexpected blocks {:?}", other),
+        };
+
+        // the columns without a ratio share what is left of the slide
+        let widths: Vec<f64> = columns.columns.iter().map(|c| c.width).collect();
+        assert!((widths[0] - 0.6).abs() < 1e-9);
+        assert!((widths[1] - 0.2).abs() < 1e-9);
+        assert!((widths.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(columns.columns[2].contents, vec![paragraph("3")]);
+
+        // a picture in a column is not split from the text of the slide
+        let blocks = vec![
+            Block::Directive(Directive::Columns {
+                alignment: ColumnAlignment::Center,
+                ratios: vec![],
+            }),
+            paragraph("text"),
+            Block::Directive(Directive::Column),
+            Block::Picture(ParsePicture {
+                path: "a.png".into(),
+                caption: None,
+                directive: None,
+            }),
+        ];
+
+        let contents = to_content_options(group_columns(blocks));
+        assert!(matches!(contents, ContentOptions::OnlyText(_)));
+    }
+
+    #[test]
+    fn column_widths() {
+        let widths = |ratios: Vec<f64>, count: usize| {
+            let columns = (0..count).map(|_| Vec::new()).collect();
+
+            match build_columns((ColumnAlignment::Top, ratios, columns)) {
+                Block::Columns(columns) => columns
+                    .columns
+                    .iter()
+                    .map(|column| column.width)
+                    .collect::<Vec<f64>>(),
+                other => panic!("unexpected block {:?}", other),
+            }
+        };
+
+        // fractions are widths of the slide
+        assert_eq!(widths(vec![0.3, 0.3], 2), vec![0.3, 0.3]);
+
+        // anything else is relative
+        assert_eq!(widths(vec![3.0, 1.0], 2), vec![0.75, 0.25]);
+        assert_eq!(widths(vec![], 2), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn nested_link_definitions() {
+        let link = Span::ReferenceLink {
+            text: spans_from_text("docs"),
+            label: "d".into(),
+            kind: ReferenceKind::Full,
+        };
+        let definition = |label: &str| Block::LinkDefinition {
+            label: label.into(),
+            url: format!("https://{}.org", label),
+        };
+
+        let mut document = Document {
+            first: ParsedTitle {
+                title: spans_from_text("title"),
+                subtitle: None,
+                metadata: Metadata::default(),
+            },
+            slides: vec![ParsedSlide {
+                title: spans_from_text("1"),
+                contents: vec![
+                    Block::Paragraph(vec![link.clone()]),
+                    Block::Quote(vec![definition("d")]),
+                ],
+                notes: vec![Block::Paragraph(vec![link]), definition("n")],
+            }],
+            sections: vec![],
+        };
+
+        resolve_reference_links(&mut document);
+
+        let resolved = Block::Paragraph(vec![Span::Link {
+            text: spans_from_text("docs"),
+            url: "https://d.org".into(),
+        }]);
+
+        assert_eq!(
+            document.slides[0].contents,
+            vec![resolved.clone(), Block::Quote(vec![])]
+        );
+        assert_eq!(document.slides[0].notes, vec![resolved]);
+
+        // a definition that is left over renders as nothing
+        let mut buffer = String::new();
+        crate::data::nested_blocks(&[definition("x")], &mut buffer);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn collapsed_and_empty_links() {
+        let markdown = "# Title\nAUTHOR=Author\n\n## Links\n\n[docs][] [u][] [t]()\n\n> [docs]: https://docs.org\n";
+        let mut document = crate::parse::parse_markdown(markdown.as_bytes()).unwrap();
+
+        resolve_reference_links(&mut document);
+
+        let mut buffer = String::new();
+        crate::data::nested_blocks(&document.slides[0].contents, &mut buffer);
+
+        assert!(buffer.contains("\\href{https://docs.org}{docs} [u][] t"));
+    }
 }
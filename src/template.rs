@@ -0,0 +1,135 @@
+use std::path::Path;
+
+/// the template used when `--template` is not given
+const DEFAULT_TEMPLATE: &str = include_str!("default_template.tex");
+
+/// a latex document with `{{name}}` placeholders that are filled in for each presentation
+#[derive(Debug, PartialEq)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum TemplateError {
+    #[error("unknown placeholder `{{{{{name}}}}}` on line {line} of the template")]
+    UnknownPlaceholder { name: String, line: usize },
+    #[error("the template has no `{{{{{0}}}}}` placeholder")]
+    MissingPlaceholder(&'static str),
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE)
+    }
+}
+
+impl Template {
+    pub(crate) fn from_file(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// split the template into text and placeholders. Braces that do not surround a placeholder
+    /// name, like the ones in `\title{{\bf x}}`, are kept as text
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut rest = text;
+        let mut literal = String::new();
+
+        while let Some(start) = rest.find("{{") {
+            // the placeholder is the innermost pair of braces, so `\title{{{title}}}` works
+            let mut start = start;
+            while rest[start + 2..].starts_with('{') {
+                start += 1;
+            }
+
+            let after = &rest[start + 2..];
+            let name_length = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(after.len());
+
+            if name_length > 0 && after[name_length..].starts_with("}}") {
+                literal.push_str(&rest[..start]);
+                segments.push(Segment::Text(std::mem::take(&mut literal)));
+                segments.push(Segment::Placeholder(after[..name_length].to_string()));
+                rest = &after[name_length + 2..];
+            } else {
+                literal.push_str(&rest[..start + 2]);
+                rest = after;
+            }
+        }
+
+        literal.push_str(rest);
+        segments.push(Segment::Text(literal));
+        segments.retain(|segment| !matches!(segment, Segment::Text(text) if text.is_empty()));
+
+        Self { segments }
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub(crate) fn has_placeholder(&self, name: &str) -> bool {
+        self.segments.iter().any(
+            |segment| matches!(segment, Segment::Placeholder(placeholder) if placeholder == name),
+        )
+    }
+
+    /// 1 indexed line that the placeholder at `index` of the segments is on
+    pub(crate) fn line_of(&self, index: usize) -> usize {
+        let newlines: usize = self.segments[..index]
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.matches('\n').count(),
+                Segment::Placeholder(_) => 0,
+            })
+            .sum();
+
+        newlines + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders() {
+        let template = Template::parse("\\title{{{title}}}\n{{slides}}\\textbf{{\\bf x}}{{ not }}");
+
+        let expected = vec![
+            Segment::Text("\\title{".into()),
+            Segment::Placeholder("title".into()),
+            Segment::Text("}\n".into()),
+            Segment::Placeholder("slides".into()),
+            Segment::Text("\\textbf{{\\bf x}}{{ not }}".into()),
+        ];
+
+        assert_eq!(template.segments, expected);
+        assert_eq!(template.line_of(3), 2);
+        assert!(template.has_placeholder("title"));
+        assert!(!template.has_placeholder("author"));
+    }
+
+    #[test]
+    fn default_template() {
+        let template = Template::default();
+
+        for name in [
+            "title",
+            "subtitle_command",
+            "author",
+            "institute_command",
+            "slides",
+            "preamble_extra",
+        ] {
+            assert!(template.has_placeholder(name), "missing {}", name);
+        }
+    }
+}
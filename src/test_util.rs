@@ -0,0 +1,31 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// a directory for the files of a test, removed again when it is dropped. `name` keeps the
+/// directories of tests that run at the same time apart
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("markdown-pdf-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // a failing test may have left the directory in any state
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
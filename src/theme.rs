@@ -0,0 +1,253 @@
+use super::metadata::Metadata;
+
+use std::fmt;
+use std::process::Command;
+
+const THEMES: &[&str] = &[
+    "default",
+    "AnnArbor",
+    "Antibes",
+    "Bergen",
+    "Berkeley",
+    "Berlin",
+    "Boadilla",
+    "boxes",
+    "CambridgeUS",
+    "Copenhagen",
+    "Darmstadt",
+    "Dresden",
+    "EastLansing",
+    "Frankfurt",
+    "Goettingen",
+    "Hannover",
+    "Ilmenau",
+    "JuanLesPins",
+    "Luebeck",
+    "Madrid",
+    "Malmoe",
+    "Marburg",
+    "Montpellier",
+    "PaloAlto",
+    "Pittsburgh",
+    "Rochester",
+    "Singapore",
+    "Szeged",
+    "Warsaw",
+];
+
+const COLOR_THEMES: &[&str] = &[
+    "default",
+    "albatross",
+    "beaver",
+    "beetle",
+    "crane",
+    "dolphin",
+    "dove",
+    "fly",
+    "lily",
+    "monarca",
+    "orchid",
+    "rose",
+    "seagull",
+    "seahorse",
+    "sidebartab",
+    "spruce",
+    "structure",
+    "whale",
+    "wolverine",
+];
+
+const FONT_THEMES: &[&str] = &[
+    "default",
+    "professionalfonts",
+    "serif",
+    "structurebold",
+    "structureitalicserif",
+    "structuresmallcapsserif",
+];
+
+const INNER_THEMES: &[&str] = &["default", "circles", "inmargin", "rectangles", "rounded"];
+
+const OUTER_THEMES: &[&str] = &[
+    "default",
+    "infolines",
+    "miniframes",
+    "shadow",
+    "sidebar",
+    "smoothbars",
+    "smoothtree",
+    "split",
+    "tree",
+];
+
+/// values of the `aspectratio` option of the beamer class
+const ASPECT_RATIOS: &[&str] = &["43", "169", "1610", "149", "141", "54", "32"];
+
+/// a theme or aspect ratio that beamer does not know about
+#[derive(Debug, PartialEq)]
+pub(crate) struct UnknownOption {
+    setting: &'static str,
+    value: String,
+    expected: &'static [&'static str],
+    /// the `.sty` file latex looks for, for themes
+    package: Option<String>,
+}
+
+impl std::error::Error for UnknownOption {}
+
+impl fmt::Display for UnknownOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} `{}`", self.setting, self.value)?;
+
+        match (closest_match(&self.value, self.expected), &self.package) {
+            (Some(suggestion), _) => write!(f, ", did you mean `{}`?", suggestion),
+            (None, Some(package)) => write!(f, ", latex will look for `{}`", package),
+            (None, None) => write!(f, ", expected one of: {}", self.expected.join(", ")),
+        }
+    }
+}
+
+/// check every theme and the aspect ratio of the presentation against the ones beamer ships with.
+/// An unknown aspect ratio is an error. Themes can also be installed locally or sit next to the
+/// `.tex` file, so unknown themes that `kpsewhich` can not find are returned as warnings
+pub(crate) fn validate(metadata: &Metadata) -> Result<Vec<UnknownOption>, UnknownOption> {
+    if let Some(value) = &metadata.aspectratio {
+        if !ASPECT_RATIOS.contains(&value.as_str()) {
+            return Err(UnknownOption {
+                setting: "aspect ratio",
+                value: value.clone(),
+                expected: ASPECT_RATIOS,
+                package: None,
+            });
+        }
+    }
+
+    let themes = [
+        ("theme", &metadata.theme, THEMES, "theme"),
+        (
+            "color theme",
+            &metadata.colortheme,
+            COLOR_THEMES,
+            "colortheme",
+        ),
+        ("font theme", &metadata.fonttheme, FONT_THEMES, "fonttheme"),
+        (
+            "inner theme",
+            &metadata.innertheme,
+            INNER_THEMES,
+            "innertheme",
+        ),
+        (
+            "outer theme",
+            &metadata.outertheme,
+            OUTER_THEMES,
+            "outertheme",
+        ),
+    ];
+
+    let mut warnings = Vec::new();
+
+    for (setting, value, expected, kind) in themes {
+        if let Some(value) = value {
+            let package = format!("beamer{}{}.sty", kind, value);
+
+            if !expected.contains(&value.as_str()) && !is_installed(&package) {
+                warnings.push(UnknownOption {
+                    setting,
+                    value: value.clone(),
+                    expected,
+                    package: Some(package),
+                });
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// whether latex can find a file, according to `kpsewhich`. Without `kpsewhich` nothing is found
+fn is_installed(file: &str) -> bool {
+    Command::new("kpsewhich")
+        .arg(file)
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.trim_ascii().is_empty())
+}
+
+/// the known value that is only a typo or two away from `value`
+fn closest_match(value: &str, expected: &[&'static str]) -> Option<&'static str> {
+    let value = value.to_lowercase();
+
+    expected
+        .iter()
+        .map(|candidate| (edit_distance(&value, &candidate.to_lowercase()), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_options() {
+        let metadata = Metadata {
+            theme: Some("Madrid".into()),
+            colortheme: Some("beaver".into()),
+            aspectratio: Some("1610".into()),
+            ..Metadata::default()
+        };
+
+        assert_eq!(validate(&metadata), Ok(vec![]));
+    }
+
+    #[test]
+    fn typos() {
+        let metadata = Metadata {
+            colortheme: Some("dolpin".into()),
+            outertheme: Some("notbeamerinstalled".into()),
+            ..Metadata::default()
+        };
+        let warnings = validate(&metadata).unwrap();
+        let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "unknown color theme `dolpin`, did you mean `dolphin`?",
+                "unknown outer theme `notbeamerinstalled`, latex will look for \
+                 `beamerouterthemenotbeamerinstalled.sty`",
+            ]
+        );
+
+        let metadata = Metadata {
+            aspectratio: Some("wide".into()),
+            ..Metadata::default()
+        };
+        let error = validate(&metadata).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "unknown aspect ratio `wide`, expected one of: 43, 169, 1610, 149, 141, 54, 32"
+        );
+    }
+}
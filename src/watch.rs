@@ -0,0 +1,67 @@
+use super::Error;
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// how often the watched files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// editors often save a file in several steps, so wait until nothing has changed for this long
+/// before rebuilding
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// modification time and length of a file, `None` if it does not exist
+type FileState = Option<(SystemTime, u64)>;
+
+/// run `build` once and then again every time one of the files it depends on changes. `build`
+/// returns the files to watch, when it fails the files of the last successful build are watched
+pub(crate) fn watch<F>(markdown_input: PathBuf, mut build: F) -> !
+where
+    F: FnMut() -> Result<Vec<PathBuf>, Error>,
+{
+    let mut files = vec![markdown_input];
+
+    loop {
+        match build() {
+            Ok(new_files) => files = new_files,
+            Err(e) => eprintln!("error: {}", e),
+        }
+
+        eprintln!("watching {} file(s) for changes", files.len());
+
+        let mut previous = snapshot(&files);
+
+        // wait for the first change
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = snapshot(&files);
+
+            if current != previous {
+                previous = current;
+                break;
+            }
+        }
+
+        // then for the files to settle
+        loop {
+            std::thread::sleep(DEBOUNCE);
+            let current = snapshot(&files);
+
+            if current == previous {
+                break;
+            }
+
+            previous = current;
+        }
+    }
+}
+
+fn snapshot(files: &[PathBuf]) -> Vec<FileState> {
+    files
+        .iter()
+        .map(|file| {
+            let metadata = std::fs::metadata(file).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}